@@ -1,15 +1,362 @@
-use clap::Parser;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::push::{BranchSelection, SuccessPolicy, TagMode, UpstreamTarget};
+use crate::reconcile::ReconcileStrategy;
 
 #[derive(Parser, Debug, PartialEq, Clone)]
 #[command(name = "andiamo")]
-#[command(about = "A CLI tool for managing git repositories with dual remotes", long_about = None)]
+#[command(about = "A CLI tool for managing git repositories with multiple remotes", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Deprecated: use `andiamo init`
     #[arg(long)]
     pub init: bool,
 
+    /// Deprecated: use `andiamo remote add`
     #[arg(long)]
     pub add_remotes: bool,
 
+    /// Deprecated: use `andiamo push`
     #[arg(long)]
     pub push: bool,
+
+    /// Comma-separated names of the remotes to operate on.
+    /// Defaults to the remotes in `.andiamo.toml`, or `origin,mirror`.
+    #[arg(long, global = true, value_delimiter = ',', env = "ANDIAMO_REMOTES")]
+    pub remotes: Option<Vec<String>>,
+
+    /// Profile from ~/.config/andiamo/config.toml supplying remote URLs
+    #[arg(long, global = true, env = "ANDIAMO_PROFILE")]
+    pub profile: Option<String>,
+
+    #[command(flatten)]
+    pub urls: RemoteUrlArgs,
+}
+
+impl Cli {
+    /// Whether any of the deprecated `--init/--add-remotes/--push` flags
+    /// were given.
+    pub fn uses_legacy_flags(&self) -> bool {
+        self.init || self.add_remotes || self.push
+    }
+}
+
+#[derive(Subcommand, Debug, PartialEq, Clone)]
+pub enum Command {
+    /// Initialize a git repository in the current directory
+    Init {
+        /// Print the git command that would run without running it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Manage the remotes andiamo pushes to
+    Remote {
+        #[command(subcommand)]
+        command: RemoteCommand,
+    },
+    /// Push the current branch to every remote
+    Push(PushArgs),
+    /// Fetch every remote at the same time
+    Fetch(FetchArgs),
+    /// Pull the current branch from the primary remote
+    Pull(PullArgs),
+    /// Show how far each remote is ahead of or behind the local branch
+    Status(StatusArgs),
+    /// Add any missing remotes, then push to all of them
+    Sync(PushArgs),
+    /// Check git, configuration and remotes for problems
+    Doctor,
+    /// Resolve a branch that diverged between the primary remote and the others
+    Reconcile(ReconcileArgs),
+    /// Copy refs from the primary remote to the others through a bare cache, without a work tree
+    Relay(RelayArgs),
+}
+
+#[derive(Subcommand, Debug, PartialEq, Clone)]
+pub enum RemoteCommand {
+    /// Add a single remote, or every configured remote when no name is given
+    Add {
+        #[arg(requires = "url")]
+        name: Option<String>,
+        url: Option<String>,
+        /// Print the git commands that would run without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List the managed remotes and their URLs
+    List,
+    /// Remove a remote from the repository
+    Remove { name: String },
+    /// Change the URL of an existing remote
+    SetUrl { name: String, url: String },
+}
+
+#[derive(Args, Debug, Default, PartialEq, Clone)]
+pub struct PushArgs {
+    /// Push to at most N remotes at the same time (default: all at once)
+    #[arg(short, long, value_name = "N", value_parser = parse_jobs)]
+    pub jobs: Option<usize>,
+
+    /// Attempt every remote even after one has failed
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// When the push counts as successful: all, primary or quorum=N
+    #[arg(long, value_name = "POLICY")]
+    pub policy: Option<SuccessPolicy>,
+
+    /// Retry network failures up to N times per remote (default: 2)
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Kill git after SECS seconds on any remote, overriding the configured timeouts
+    #[arg(long, value_name = "SECS", value_parser = parse_timeout)]
+    pub timeout: Option<Duration>,
+
+    /// Push every local branch instead of the current one
+    #[arg(long, conflicts_with_all = ["branch", "tag"])]
+    pub all: bool,
+
+    /// Push the commit HEAD points at to this branch on every remote, e.g. with a detached HEAD
+    #[arg(long, value_name = "refs/heads/NAME", value_parser = parse_to_ref, conflicts_with_all = ["all", "branch", "tag"])]
+    pub to_ref: Option<String>,
+
+    /// Push the local branches matching GLOB instead of the current one (repeatable)
+    #[arg(long, value_name = "GLOB", conflicts_with = "tag")]
+    pub branch: Vec<String>,
+
+    /// Also push every local tag
+    #[arg(long, conflicts_with_all = ["follow_tags", "tag"])]
+    pub tags: bool,
+
+    /// Also push the annotated tags reachable from the branch
+    #[arg(long, conflicts_with = "tag")]
+    pub follow_tags: bool,
+
+    /// Push only this tag to every remote, instead of the branch
+    #[arg(long, value_name = "NAME")]
+    pub tag: Option<String>,
+
+    /// Allow rewritten branches, as long as each remote still matches its remote-tracking branch
+    #[arg(long, conflicts_with = "tag")]
+    pub force_with_lease: bool,
+
+    /// Update all refs on each remote or none of them, e.g. a branch and its tags
+    #[arg(long)]
+    pub atomic: bool,
+
+    /// Remote that pushed branches without an upstream start tracking: primary (default), none or a remote name
+    #[arg(long, value_name = "REMOTE")]
+    pub set_upstream: Option<UpstreamTarget>,
+
+    /// Do not check each remote's refs with `git ls-remote` after pushing
+    #[arg(long)]
+    pub skip_verify: bool,
+
+    /// Fetch every remote first and refuse to push if a branch has diverged between them (default)
+    #[arg(long)]
+    pub check_divergence: bool,
+
+    /// Push without first checking whether branches have diverged between remotes
+    #[arg(long, conflicts_with = "check_divergence")]
+    pub no_check_divergence: bool,
+
+    /// Delete refs on mirror remotes without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Preview each remote's ref updates with `git push --dry-run` without pushing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl PushArgs {
+    /// The branches selected by `--to-ref`, `--all` or `--branch`.
+    pub fn branch_selection(&self) -> BranchSelection {
+        if let Some(branch) = &self.to_ref {
+            BranchSelection::HeadTo(branch.clone())
+        } else if self.all {
+            BranchSelection::All
+        } else if !self.branch.is_empty() {
+            BranchSelection::Matching(self.branch.clone())
+        } else {
+            BranchSelection::Current
+        }
+    }
+
+    /// The tag mode selected by `--tags`, `--follow-tags` or `--tag`.
+    pub fn tag_mode(&self) -> TagMode {
+        if let Some(tag) = &self.tag {
+            TagMode::Single(tag.clone())
+        } else if self.tags {
+            TagMode::All
+        } else if self.follow_tags {
+            TagMode::Follow
+        } else {
+            TagMode::None
+        }
+    }
+}
+
+#[derive(Args, Debug, Default, PartialEq, Clone)]
+pub struct StatusArgs {
+    /// Show every local branch instead of only the current one
+    #[arg(long)]
+    pub all: bool,
+
+    /// Fetch every remote first instead of using the cached remote-tracking refs
+    #[arg(long)]
+    pub fetch: bool,
+
+    /// Print the status as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Default, PartialEq, Clone)]
+pub struct FetchArgs {
+    /// Remove remote-tracking refs whose branch no longer exists on the remote
+    #[arg(short, long)]
+    pub prune: bool,
+}
+
+#[derive(Args, Debug, Default, PartialEq, Clone)]
+pub struct PullArgs {
+    /// Pull from the other remotes in turn when the primary cannot be reached
+    #[arg(long)]
+    pub failover: bool,
+}
+
+#[derive(Args, Debug, PartialEq, Clone)]
+pub struct ReconcileArgs {
+    /// overwrite: force the primary's branch onto the other remotes;
+    /// merge: merge every side into the local branch and push it everywhere;
+    /// review: keep each remote's own commits on a local review/<remote>/<branch> branch
+    #[arg(long, value_name = "STRATEGY")]
+    pub strategy: ReconcileStrategy,
+
+    /// Branch to reconcile (default: the current branch)
+    #[arg(long, value_name = "NAME")]
+    pub branch: Option<String>,
+
+    /// Overwrite without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Show the divergence and the git commands that would run, without running them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug, PartialEq, Clone)]
+pub struct RelayArgs {
+    /// Bare repository to keep the relayed refs in; created when missing
+    #[arg(long, value_name = "DIR")]
+    pub cache: PathBuf,
+
+    /// Remote to fetch from (default: the primary remote); every other remote receives the refs
+    #[arg(long, value_name = "REMOTE")]
+    pub from: Option<String>,
+
+    /// Ref pattern to relay, e.g. refs/heads/main or refs/tags/v* (repeatable; default: all branches and tags)
+    #[arg(long = "ref", value_name = "PATTERN")]
+    pub refs: Vec<String>,
+
+    /// Delete refs matching the patterns from the destinations when the source no longer has them
+    #[arg(long)]
+    pub prune: bool,
+
+    /// Fetch into the cache, then preview each destination's ref updates without pushing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Remote URLs supplied up front instead of being prompted for.
+///
+/// Any remote's URL can also come from an `ANDIAMO_<NAME>_URL` environment
+/// variable, e.g. `ANDIAMO_ORIGIN_URL` or `ANDIAMO_NAS_BACKUP_URL`.
+#[derive(Args, Debug, Default, PartialEq, Clone)]
+pub struct RemoteUrlArgs {
+    /// URL for the 'origin' remote
+    #[arg(long, global = true)]
+    pub origin_url: Option<String>,
+
+    /// URL for the 'mirror' remote
+    #[arg(long, global = true)]
+    pub mirror_url: Option<String>,
+
+    /// URL for any remote as NAME=URL (repeatable)
+    #[arg(long = "remote", global = true, value_name = "NAME=URL", value_parser = parse_remote_spec)]
+    pub remote: Vec<(String, String)>,
+
+    /// Never prompt; fail if a required URL is missing
+    #[arg(
+        long,
+        global = true,
+        env = "ANDIAMO_NON_INTERACTIVE",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub non_interactive: bool,
+}
+
+impl RemoteUrlArgs {
+    /// All URLs given on the command line as `(name, url)` pairs, in the
+    /// order `--origin-url`, `--mirror-url`, then each `--remote`.
+    pub fn overrides(&self) -> Vec<(String, String)> {
+        let mut overrides = Vec::new();
+
+        if let Some(url) = &self.origin_url {
+            overrides.push(("origin".to_string(), url.clone()));
+        }
+        if let Some(url) = &self.mirror_url {
+            overrides.push(("mirror".to_string(), url.clone()));
+        }
+        overrides.extend(self.remote.iter().cloned());
+
+        overrides
+    }
+}
+
+/// Parses a `NAME=URL` remote specification.
+pub fn parse_remote_spec(spec: &str) -> Result<(String, String), String> {
+    match spec.split_once('=') {
+        Some((name, url)) if !name.is_empty() && !url.is_empty() => {
+            Ok((name.to_string(), url.to_string()))
+        }
+        _ => Err(format!("expected NAME=URL, got '{}'", spec)),
+    }
+}
+
+/// Parses a `--jobs` value, which must be at least 1.
+pub fn parse_jobs(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(jobs) if jobs >= 1 => Ok(jobs),
+        _ => Err(format!("expected a number of at least 1, got '{}'", value)),
+    }
+}
+
+/// Parses a `--timeout` value in whole seconds, which must be at least 1.
+pub fn parse_timeout(value: &str) -> Result<Duration, String> {
+    match value.parse::<u64>() {
+        Ok(secs) if secs >= 1 => Ok(Duration::from_secs(secs)),
+        _ => Err(format!(
+            "expected a number of seconds of at least 1, got '{}'",
+            value
+        )),
+    }
+}
+
+/// Parses a `--to-ref` value of the form `refs/heads/<name>` into the
+/// branch name.
+pub fn parse_to_ref(value: &str) -> Result<String, String> {
+    match value.strip_prefix("refs/heads/") {
+        Some(name) if !name.is_empty() && !name.ends_with('/') => Ok(name.to_string()),
+        _ => Err(format!("expected refs/heads/<name>, got '{}'", value)),
+    }
 }
@@ -0,0 +1,579 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::git_helpers::{GitError, GitResult};
+use crate::push::{SuccessPolicy, UpstreamTarget};
+use crate::remotes::{Remote, RemoteMode, RemoteRole, RemoteSet, validate_remote_name};
+
+/// Name of the per-repository configuration file, looked up at the root of
+/// the work tree.
+pub const CONFIG_FILE_NAME: &str = ".andiamo.toml";
+
+/// Placeholder in profile URLs replaced by the repository name.
+pub const REPO_PLACEHOLDER: &str = "{repo}";
+
+/// Contents of a `.andiamo.toml` file.
+///
+/// ```toml
+/// [push]
+/// push_options = ["ci.skip"]
+/// jobs = 2
+/// policy = "primary"
+/// timeout_secs = 120
+///
+/// [[remotes]]
+/// name = "origin"
+/// url = "git@gitlab.com:team/project.git"
+/// role = "primary"
+///
+/// [[remotes]]
+/// name = "nas-backup"
+/// url = "ssh://nas.local/srv/git/project.git"
+/// branches = ["main", "release/*"]
+///
+/// [[remotes]]
+/// name = "backup"
+/// url = "ssh://backup.local/srv/git/project.git"
+/// mode = "mirror"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    #[serde(default)]
+    pub remotes: Vec<RemoteConfig>,
+    #[serde(default)]
+    pub push: PushConfig,
+}
+
+/// A `[[remotes]]` entry.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    pub name: String,
+    pub url: Option<String>,
+    /// `"primary"` or `"secondary"`; `origin` is primary by default.
+    pub role: Option<RemoteRole>,
+    /// `"branch"` (the default) or `"mirror"` to replicate every ref and
+    /// prune refs deleted locally.
+    pub mode: Option<RemoteMode>,
+    /// Branch patterns pushed to this remote; every branch when empty.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Extra `--push-option` values for this remote only.
+    #[serde(default)]
+    pub push_options: Vec<String>,
+    /// Seconds git may run against this remote before it is killed;
+    /// overrides `push.timeout_secs`.
+    pub timeout_secs: Option<u64>,
+}
+
+/// The `[push]` table, applying to every remote.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PushConfig {
+    #[serde(default)]
+    pub push_options: Vec<String>,
+    /// Maximum number of remotes pushed at the same time.
+    pub jobs: Option<usize>,
+    /// Attempt every remote even after one has failed.
+    pub keep_going: Option<bool>,
+    /// Update all refs on a remote or none of them (`git push --atomic`).
+    pub atomic: Option<bool>,
+    /// Remote that pushed branches start tracking: `"primary"`, `"none"`
+    /// or a remote name.
+    pub upstream: Option<UpstreamTarget>,
+    /// Check each remote's refs with `git ls-remote` after pushing.
+    pub verify: Option<bool>,
+    /// Fetch first and refuse to push branches that diverged between
+    /// remotes. On by default.
+    pub check_divergence: Option<bool>,
+    /// When a push counts as successful: `"all"`, `"primary"` or
+    /// `"quorum=N"`.
+    pub policy: Option<SuccessPolicy>,
+    /// Seconds git may run against a remote before it is killed.
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+/// The `[push.retry]` table, controlling retries of transient failures.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Attempts after the first one.
+    pub retries: Option<u32>,
+    /// Delay before the first retry in milliseconds, doubled each time.
+    pub backoff_ms: Option<u64>,
+    /// Upper bound for the delay in milliseconds.
+    pub max_backoff_ms: Option<u64>,
+    /// Fraction of each delay, between 0 and 1, that is randomised.
+    pub jitter: Option<f64>,
+}
+
+impl RepoConfig {
+    /// Parses and validates configuration text. `path` is only used to
+    /// label errors.
+    pub fn parse(contents: &str, path: &Path) -> GitResult<Self> {
+        let config: RepoConfig =
+            toml::from_str(contents).map_err(|e| config_error(path, e.to_string()))?;
+
+        config
+            .validate()
+            .map_err(|message| config_error(path, message))?;
+
+        Ok(config)
+    }
+
+    /// Reads and validates the configuration file at `path`.
+    pub fn load(path: &Path) -> GitResult<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| config_error(path, format!("could not read file: {}", e)))?;
+
+        RepoConfig::parse(&contents, path)
+    }
+
+    /// Loads `.andiamo.toml` from the root of the current work tree, or from
+    /// the current directory outside a repository. Returns `None` when no
+    /// file exists.
+    pub fn discover() -> GitResult<Option<(PathBuf, Self)>> {
+        let path = config_root().join(CONFIG_FILE_NAME);
+
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let config = RepoConfig::load(&path)?;
+        Ok(Some((path, config)))
+    }
+
+    /// Checks the invariants serde cannot express, returning a
+    /// human-readable description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen: Vec<&str> = Vec::new();
+
+        for (index, remote) in self.remotes.iter().enumerate() {
+            let label = format!("remotes[{}] ('{}')", index, remote.name);
+
+            if validate_remote_name(&remote.name).is_err() {
+                return Err(format!("{}: invalid remote name", label));
+            }
+
+            if seen.contains(&remote.name.as_str()) {
+                return Err(format!("{}: duplicate remote name", label));
+            }
+            seen.push(&remote.name);
+
+            if let Some(url) = &remote.url
+                && url.trim().is_empty()
+            {
+                return Err(format!("{}: url must not be empty", label));
+            }
+
+            if remote
+                .branches
+                .iter()
+                .any(|pattern| pattern.trim().is_empty())
+            {
+                return Err(format!("{}: branch patterns must not be empty", label));
+            }
+
+            if remote.mode == Some(RemoteMode::Mirror) && !remote.branches.is_empty() {
+                return Err(format!(
+                    "{}: branches cannot be combined with mode = \"mirror\"",
+                    label
+                ));
+            }
+
+            validate_push_options(&remote.push_options).map_err(|e| format!("{}: {}", label, e))?;
+
+            if remote.timeout_secs == Some(0) {
+                return Err(format!("{}: timeout_secs must be at least 1", label));
+            }
+        }
+
+        validate_push_options(&self.push.push_options).map_err(|e| format!("push: {}", e))?;
+
+        if self.push.jobs == Some(0) {
+            return Err("push: jobs must be at least 1".to_string());
+        }
+
+        if self.push.timeout_secs == Some(0) {
+            return Err("push: timeout_secs must be at least 1".to_string());
+        }
+
+        if let Some(jitter) = self.push.retry.jitter
+            && !(0.0..=1.0).contains(&jitter)
+        {
+            return Err("push.retry: jitter must be between 0 and 1".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Returns a copy with `{repo}` in every remote URL replaced by
+    /// `repo_name`.
+    pub fn expand_templates(&self, repo_name: &str) -> Result<RepoConfig, String> {
+        let mut expanded = self.clone();
+
+        for remote in &mut expanded.remotes {
+            if let Some(url) = &remote.url {
+                let url = url.replace(REPO_PLACEHOLDER, repo_name);
+                if url.contains('{') || url.contains('}') {
+                    return Err(format!(
+                        "remote '{}': unknown placeholder in url (only {} is supported)",
+                        remote.name, REPO_PLACEHOLDER
+                    ));
+                }
+                remote.url = Some(url);
+            }
+        }
+
+        Ok(expanded)
+    }
+
+    /// Layers `overrides` on top of this configuration. Remotes are matched
+    /// by name; any value set in `overrides` wins, and remotes only present
+    /// there are appended.
+    pub fn overlay(&self, overrides: &RepoConfig) -> RepoConfig {
+        let mut merged = self.clone();
+
+        for remote in &overrides.remotes {
+            match merged
+                .remotes
+                .iter_mut()
+                .find(|base| base.name == remote.name)
+            {
+                Some(base) => {
+                    if remote.url.is_some() {
+                        base.url = remote.url.clone();
+                    }
+                    if remote.role.is_some() {
+                        base.role = remote.role;
+                    }
+                    if remote.mode.is_some() {
+                        base.mode = remote.mode;
+                    }
+                    if !remote.branches.is_empty() {
+                        base.branches = remote.branches.clone();
+                    }
+                    if !remote.push_options.is_empty() {
+                        base.push_options = remote.push_options.clone();
+                    }
+                    if remote.timeout_secs.is_some() {
+                        base.timeout_secs = remote.timeout_secs;
+                    }
+                }
+                None => merged.remotes.push(remote.clone()),
+            }
+        }
+
+        if !overrides.push.push_options.is_empty() {
+            merged.push.push_options = overrides.push.push_options.clone();
+        }
+        if overrides.push.jobs.is_some() {
+            merged.push.jobs = overrides.push.jobs;
+        }
+        if overrides.push.keep_going.is_some() {
+            merged.push.keep_going = overrides.push.keep_going;
+        }
+        if overrides.push.atomic.is_some() {
+            merged.push.atomic = overrides.push.atomic;
+        }
+        if overrides.push.upstream.is_some() {
+            merged.push.upstream = overrides.push.upstream.clone();
+        }
+        if overrides.push.verify.is_some() {
+            merged.push.verify = overrides.push.verify;
+        }
+        if overrides.push.check_divergence.is_some() {
+            merged.push.check_divergence = overrides.push.check_divergence;
+        }
+        if overrides.push.policy.is_some() {
+            merged.push.policy = overrides.push.policy;
+        }
+        if overrides.push.timeout_secs.is_some() {
+            merged.push.timeout_secs = overrides.push.timeout_secs;
+        }
+
+        let (retry, overrides) = (&mut merged.push.retry, &overrides.push.retry);
+        if overrides.retries.is_some() {
+            retry.retries = overrides.retries;
+        }
+        if overrides.backoff_ms.is_some() {
+            retry.backoff_ms = overrides.backoff_ms;
+        }
+        if overrides.max_backoff_ms.is_some() {
+            retry.max_backoff_ms = overrides.max_backoff_ms;
+        }
+        if overrides.jitter.is_some() {
+            retry.jitter = overrides.jitter;
+        }
+
+        merged
+    }
+
+    pub fn remote_config(&self, name: &str) -> Option<&RemoteConfig> {
+        self.remotes.iter().find(|remote| remote.name == name)
+    }
+
+    /// Converts this configuration into a [`RemoteSet`], folding the global
+    /// `[push]` options and timeout into every remote.
+    pub fn remote_set(&self) -> GitResult<RemoteSet> {
+        let mut remotes = RemoteSet::new();
+
+        for remote in &self.remotes {
+            remotes.insert(self.to_remote(remote))?;
+        }
+
+        Ok(remotes)
+    }
+
+    fn to_remote(&self, remote: &RemoteConfig) -> Remote {
+        let mut push_options = self.push.push_options.clone();
+        push_options.extend(remote.push_options.iter().cloned());
+
+        Remote {
+            name: remote.name.clone(),
+            url: remote.url.clone(),
+            role: remote
+                .role
+                .unwrap_or_else(|| RemoteRole::default_for(&remote.name)),
+            mode: remote.mode.unwrap_or_default(),
+            branches: remote.branches.clone(),
+            push_options,
+            timeout: remote
+                .timeout_secs
+                .or(self.push.timeout_secs)
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+/// Contents of the user-level `~/.config/andiamo/config.toml`.
+///
+/// Each profile has the same shape as a `.andiamo.toml`, with `{repo}`
+/// available in remote URLs:
+///
+/// ```toml
+/// [[profiles.work.remotes]]
+/// name = "origin"
+/// url = "git@gitlab.com:team/{repo}.git"
+///
+/// [[profiles.work.remotes]]
+/// name = "mirror"
+/// url = "https://gitea.internal/team/{repo}.git"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GlobalConfig {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, RepoConfig>,
+}
+
+impl GlobalConfig {
+    /// Parses and validates configuration text. `path` is only used to
+    /// label errors.
+    pub fn parse(contents: &str, path: &Path) -> GitResult<Self> {
+        let config: GlobalConfig =
+            toml::from_str(contents).map_err(|e| config_error(path, e.to_string()))?;
+
+        for (name, profile) in &config.profiles {
+            profile
+                .validate()
+                .map_err(|message| config_error(path, format!("profiles.{}.{}", name, message)))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Reads and validates the configuration file at `path`.
+    pub fn load(path: &Path) -> GitResult<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| config_error(path, format!("could not read file: {}", e)))?;
+
+        GlobalConfig::parse(&contents, path)
+    }
+
+    /// Location of the user-level file: `$XDG_CONFIG_HOME/andiamo/config.toml`,
+    /// falling back to `$HOME/.config/andiamo/config.toml`.
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(base.join("andiamo").join("config.toml"))
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&RepoConfig> {
+        self.profiles.get(name)
+    }
+}
+
+/// Builds the configuration a command runs with: the named profile from the
+/// user-level file with `{repo}` filled in, overridden by the repository's
+/// `.andiamo.toml`. Returns `None` when neither applies.
+pub fn load_effective_config(profile: Option<&str>) -> GitResult<Option<RepoConfig>> {
+    let repo_config = RepoConfig::discover()?.map(|(_, config)| config);
+
+    let Some(profile_name) = profile else {
+        return Ok(repo_config);
+    };
+
+    let path = GlobalConfig::path().ok_or_else(|| {
+        config_error(
+            Path::new("~/.config/andiamo/config.toml"),
+            "could not determine the home directory".to_string(),
+        )
+    })?;
+
+    if !path.is_file() {
+        return Err(config_error(
+            &path,
+            format!(
+                "profile '{}' requested but the file does not exist",
+                profile_name
+            ),
+        ));
+    }
+
+    let global = GlobalConfig::load(&path)?;
+
+    let profile = global
+        .profile(profile_name)
+        .ok_or_else(|| config_error(&path, format!("profile '{}' is not defined", profile_name)))?;
+
+    let profile = profile.expand_templates(&repo_name()).map_err(|message| {
+        config_error(&path, format!("profiles.{}: {}", profile_name, message))
+    })?;
+
+    Ok(Some(match repo_config {
+        Some(repo_config) => profile.overlay(&repo_config),
+        None => profile,
+    }))
+}
+
+/// Name of the current repository, taken from the work tree directory.
+pub fn repo_name() -> String {
+    let root = config_root();
+    let root = root.canonicalize().unwrap_or(root);
+
+    root.file_name()
+        .map(|name| name.to_string_lossy().trim_end_matches(".git").to_string())
+        .unwrap_or_default()
+}
+
+/// Works out which remotes a command operates on.
+///
+/// Names given on the command line win; each one still picks up its URL and
+/// settings from the configuration when it is declared there. Without
+/// explicit names the configured remotes are used, and without a
+/// configuration the default `origin` + `mirror` pair.
+pub fn resolve_remotes(
+    names: Option<&[String]>,
+    config: Option<&RepoConfig>,
+) -> GitResult<RemoteSet> {
+    let Some(names) = names else {
+        return match config {
+            Some(config) if !config.remotes.is_empty() => config.remote_set(),
+            _ => Ok(RemoteSet::with_defaults()),
+        };
+    };
+
+    let mut remotes = RemoteSet::new();
+
+    for name in names {
+        let remote = config
+            .and_then(|config| {
+                config
+                    .remote_config(name)
+                    .map(|remote| config.to_remote(remote))
+            })
+            .unwrap_or_else(|| Remote::new(name.clone(), None));
+
+        remotes.insert(remote)?;
+    }
+
+    Ok(remotes)
+}
+
+/// Environment variable holding the URL for remote `name`, e.g.
+/// `ANDIAMO_NAS_BACKUP_URL` for `nas-backup`.
+pub fn url_env_var(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("ANDIAMO_{}_URL", name)
+}
+
+/// Fills in remote URLs from `ANDIAMO_<NAME>_URL` variables, which take
+/// precedence over configuration files.
+pub fn apply_env_urls(remotes: &mut RemoteSet) {
+    for remote in remotes.iter_mut() {
+        if let Some(url) = env::var(url_env_var(&remote.name))
+            .ok()
+            .filter(|url| !url.is_empty())
+        {
+            remote.url = Some(url);
+        }
+    }
+}
+
+/// Applies `(name, url)` pairs given on the command line, adding remotes
+/// that are not part of the set yet.
+pub fn apply_url_overrides(
+    remotes: &mut RemoteSet,
+    overrides: &[(String, String)],
+) -> GitResult<()> {
+    for (name, url) in overrides {
+        match remotes.get_mut(name) {
+            Some(remote) => remote.url = Some(url.clone()),
+            None => remotes.insert(Remote::new(name.clone(), Some(url.clone())))?,
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_push_options(options: &[String]) -> Result<(), String> {
+    if options.iter().any(|option| option.contains(['\n', '\0'])) {
+        return Err("push options must not contain newlines or NUL bytes".to_string());
+    }
+
+    Ok(())
+}
+
+fn config_root() -> PathBuf {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            PathBuf::from(stdout.trim())
+        }
+        _ => PathBuf::from("."),
+    }
+}
+
+fn config_error(path: &Path, message: String) -> GitError {
+    GitError::InvalidConfig {
+        path: path.to_path_buf(),
+        message,
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::fetch::{FetchError, fetch_remotes, fetch_remotes_into};
+use crate::git_helpers::{GitError, GitResult, merge_base, resolve_ref};
+use crate::remotes::{Remote, RemoteSet};
+
+/// Commits listed per side before the rest are summarised.
+const LISTED_COMMITS: usize = 10;
+
+/// Where [`check_divergences`] fetches the remotes' branches, so that the
+/// remote-tracking refs `--force-with-lease` compares against stay as the
+/// user last saw them.
+const CHECK_NAMESPACE: &str = "refs/andiamo/check";
+
+/// A commit on only one side of a divergence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub sha: String,
+    pub subject: String,
+}
+
+/// A branch whose histories on two remotes have split: neither remote's
+/// branch contains the other's, so pushing one history to both would be
+/// rejected or, when forced, lose commits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub branch: String,
+    pub remote: String,
+    pub other: String,
+    /// The last commit both sides share; `None` for unrelated histories.
+    pub merge_base: Option<String>,
+    /// Commits only `remote` has, newest first.
+    pub only_remote: Vec<Commit>,
+    /// Commits only `other` has, newest first.
+    pub only_other: Vec<Commit>,
+}
+
+/// Fetches the remotes in `remotes` and compares `branches` between every
+/// pair of them, returning each branch and pair of remotes whose histories
+/// have diverged. A remote that cannot be fetched is compared from its
+/// cached remote-tracking refs, with a warning; remotes without the branch
+/// are left out.
+pub fn find_divergences(remotes: &RemoteSet, branches: &[String]) -> GitResult<Vec<Divergence>> {
+    let all: Vec<&Remote> = remotes.iter().collect();
+    warn_unfetched(&fetch_remotes(&all));
+
+    compare(&all, branches, |remote, branch| {
+        format!("refs/remotes/{}/{}", remote, branch)
+    })
+}
+
+/// The check `andiamo push` runs before pushing. Like [`find_divergences`],
+/// but the remotes are fetched into a scratch namespace that is deleted
+/// again afterwards, leaving the remote-tracking refs untouched, and
+/// `timeout` replaces each remote's own when set. Mirror remotes are left
+/// out, since they are force-pushed to anyway; with fewer than two remotes
+/// left nothing is fetched.
+pub fn check_divergences(
+    remotes: &RemoteSet,
+    branches: &[String],
+    timeout: Option<Duration>,
+) -> GitResult<Vec<Divergence>> {
+    let compared: Vec<Remote> = remotes
+        .iter()
+        .filter(|remote| !remote.is_mirror())
+        .map(|remote| Remote {
+            timeout: timeout.or(remote.timeout),
+            ..remote.clone()
+        })
+        .collect();
+    if compared.len() < 2 {
+        return Ok(Vec::new());
+    }
+
+    let compared: Vec<&Remote> = compared.iter().collect();
+    let failed = fetch_remotes_into(&compared, CHECK_NAMESPACE);
+    warn_unfetched(&failed);
+
+    let divergences = compare(&compared, branches, |remote, branch| {
+        if failed.iter().any(|error| error.remote == remote) {
+            format!("refs/remotes/{}/{}", remote, branch)
+        } else {
+            format!("{}/{}/{}", CHECK_NAMESPACE, remote, branch)
+        }
+    });
+    delete_namespace(CHECK_NAMESPACE)?;
+
+    divergences
+}
+
+fn warn_unfetched(errors: &[FetchError]) {
+    for error in errors {
+        println!(
+            "Warning: could not fetch {}, comparing its cached refs: {}",
+            error.remote, error.message
+        );
+    }
+}
+
+/// Compares `branches` between every pair of `remotes`, reading each
+/// remote's branch from the ref `branch_ref(remote, branch)` names.
+fn compare(
+    remotes: &[&Remote],
+    branches: &[String],
+    branch_ref: impl Fn(&str, &str) -> String,
+) -> GitResult<Vec<Divergence>> {
+    let mut divergences = Vec::new();
+    for branch in branches {
+        let tips: Vec<(&str, String, String)> = remotes
+            .iter()
+            .filter_map(|remote| {
+                let tip = branch_ref(&remote.name, branch);
+                resolve_ref(&tip).map(|sha| (remote.name.as_str(), tip, sha))
+            })
+            .collect();
+
+        for (index, (other, other_ref, other_sha)) in tips.iter().enumerate() {
+            for (remote, remote_ref, remote_sha) in &tips[index + 1..] {
+                let base = merge_base(remote_ref, other_ref);
+                if base.as_ref() == Some(remote_sha) || base.as_ref() == Some(other_sha) {
+                    continue;
+                }
+
+                divergences.push(Divergence {
+                    branch: branch.clone(),
+                    remote: remote.to_string(),
+                    other: other.to_string(),
+                    merge_base: base,
+                    only_remote: commits_between(other_ref, remote_ref)?,
+                    only_other: commits_between(remote_ref, other_ref)?,
+                });
+            }
+        }
+    }
+
+    Ok(divergences)
+}
+
+/// Deletes every ref under `namespace`.
+fn delete_namespace(namespace: &str) -> GitResult<()> {
+    let output = Command::new("git")
+        .args(["for-each-ref", "--format=delete %(refname)", namespace])
+        .output()?;
+    if output.stdout.is_empty() {
+        return Ok(());
+    }
+
+    let mut child = Command::new("git")
+        .args(["update-ref", "--stdin"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&output.stdout)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitError::CommandFailed(format!(
+            "Error deleting {}: {}",
+            namespace, stderr
+        )));
+    }
+
+    Ok(())
+}
+
+/// Commits reachable from `to` but not from `from`, newest first.
+fn commits_between(from: &str, to: &str) -> GitResult<Vec<Commit>> {
+    let range = format!("{}..{}", from, to);
+    let output = Command::new("git")
+        .args(["log", "--format=%H%x09%s", &range])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Ok(stdout
+                .lines()
+                .filter_map(|line| line.split_once('\t'))
+                .map(|(sha, subject)| Commit {
+                    sha: sha.to_string(),
+                    subject: subject.to_string(),
+                })
+                .collect())
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(GitError::CommandFailed(format!(
+                "Error listing the commits in {}: {}",
+                range, stderr
+            )))
+        }
+        Err(e) => Err(GitError::CommandFailed(format!(
+            "Error listing the commits in {}: {}",
+            range, e
+        ))),
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Branch {} has diverged between {} and {}",
+            self.branch, self.other, self.remote
+        )?;
+        match &self.merge_base {
+            Some(base) => writeln!(f, " (merge-base {}):", &base[..base.len().min(7)])?,
+            None => writeln!(f, " (no common history):")?,
+        }
+
+        for (remote, commits) in [
+            (&self.other, &self.only_other),
+            (&self.remote, &self.only_remote),
+        ] {
+            writeln!(f, "  only on {}:", remote)?;
+            for commit in commits.iter().take(LISTED_COMMITS) {
+                writeln!(f, "    {} {}", &commit.sha[..7], commit.subject)?;
+            }
+            if commits.len() > LISTED_COMMITS {
+                writeln!(f, "    ... and {} more", commits.len() - LISTED_COMMITS)?;
+            }
+        }
+
+        Ok(())
+    }
+}
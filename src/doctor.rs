@@ -0,0 +1,142 @@
+use std::process::Command;
+
+use crate::config::{load_effective_config, resolve_remotes};
+use crate::git_helpers::{
+    GitError, check_git_installed, current_branch, get_existing_remotes, get_remote_url,
+    is_git_repo,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The outcome of a single `andiamo doctor` check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every health check for the current directory. Later checks are
+/// skipped when an earlier one makes them meaningless, e.g. remote checks
+/// outside a repository.
+pub fn run_checks(profile: Option<&str>, names: Option<&[String]>) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    if !check_git_installed() {
+        checks.push(Check::new(
+            "git",
+            CheckStatus::Error,
+            "git is not installed",
+        ));
+        return checks;
+    }
+    checks.push(Check::new("git", CheckStatus::Ok, git_version()));
+
+    if !is_git_repo() {
+        checks.push(Check::new(
+            "repository",
+            CheckStatus::Error,
+            "not inside a git work tree",
+        ));
+        return checks;
+    }
+    checks.push(Check::new(
+        "repository",
+        CheckStatus::Ok,
+        "inside a git work tree",
+    ));
+
+    let config = match load_effective_config(profile) {
+        Ok(Some(config)) => {
+            checks.push(Check::new(
+                "config",
+                CheckStatus::Ok,
+                "configuration loaded",
+            ));
+            Some(config)
+        }
+        Ok(None) => {
+            checks.push(Check::new(
+                "config",
+                CheckStatus::Ok,
+                "no configuration, using defaults",
+            ));
+            None
+        }
+        Err(e) => {
+            checks.push(Check::new("config", CheckStatus::Error, e.to_string()));
+            None
+        }
+    };
+
+    match current_branch() {
+        Err(GitError::DetachedHead) => checks.push(Check::new(
+            "branch",
+            CheckStatus::Warning,
+            "HEAD is detached",
+        )),
+        Ok(branch) => checks.push(Check::new(
+            "branch",
+            CheckStatus::Ok,
+            format!("on {}", branch),
+        )),
+        Err(e) => checks.push(Check::new("branch", CheckStatus::Warning, e.to_string())),
+    }
+
+    let remotes = match resolve_remotes(names, config.as_ref()) {
+        Ok(remotes) => remotes,
+        Err(e) => {
+            checks.push(Check::new("remotes", CheckStatus::Error, e.to_string()));
+            return checks;
+        }
+    };
+
+    let existing = get_existing_remotes();
+
+    for remote in &remotes {
+        let name = format!("remote {}", remote.name);
+
+        if !existing.contains(&remote.name) {
+            checks.push(Check::new(
+                name,
+                CheckStatus::Error,
+                "not added; run `andiamo remote add`",
+            ));
+            continue;
+        }
+
+        let actual = get_remote_url(&remote.name).unwrap_or_default();
+        match &remote.url {
+            Some(expected) if *expected != actual => checks.push(Check::new(
+                name,
+                CheckStatus::Warning,
+                format!("URL is {} but configuration says {}", actual, expected),
+            )),
+            _ => checks.push(Check::new(name, CheckStatus::Ok, actual)),
+        }
+    }
+
+    checks
+}
+
+fn git_version() -> String {
+    match Command::new("git").arg("--version").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        Err(e) => e.to_string(),
+    }
+}
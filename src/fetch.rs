@@ -0,0 +1,309 @@
+use std::fmt;
+use std::process::Command;
+
+use serde::Serialize;
+
+use crate::git_helpers::{
+    GitError, GitResult, current_branch, ensure_remotes_exist, get_existing_remotes, is_git_repo,
+    run_with_timeout,
+};
+use crate::parallel::run_parallel;
+use crate::remotes::{Remote, RemoteSet};
+use crate::report::{RemoteOutcome, failed_remotes, write_outcomes};
+use crate::retry::{FailureKind, classify_failure};
+
+/// A remote that could not be fetched, with git's explanation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FetchError {
+    pub remote: String,
+    pub message: String,
+}
+
+/// Settings for a single `fetch_from_remotes` run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FetchOptions {
+    /// Delete remote-tracking refs whose branch is gone from the remote.
+    pub prune: bool,
+}
+
+/// How fetching one remote went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteFetchResult {
+    pub remote: String,
+    /// The remote-tracking refs git updated, as git lists them, e.g.
+    /// `abc1234..def5678  main -> origin/main`.
+    pub updates: Vec<String>,
+    /// Why the fetch failed; `None` when it succeeded.
+    pub failure: Option<FailureKind>,
+    /// git's error output for a failed fetch.
+    pub message: String,
+}
+
+impl RemoteFetchResult {
+    pub fn is_success(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl RemoteOutcome for RemoteFetchResult {
+    fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    fn failure(&self) -> Option<FailureKind> {
+        self.failure
+    }
+
+    fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// The outcome of fetching every remote, in the order of the remote set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchReport {
+    pub results: Vec<RemoteFetchResult>,
+}
+
+impl FetchReport {
+    /// Turns the report into an error when any remote could not be fetched.
+    pub fn check(&self) -> GitResult<()> {
+        match failed_remotes(&self.results) {
+            None => Ok(()),
+            Some((remotes, partial)) => Err(GitError::FetchFailed { remotes, partial }),
+        }
+    }
+}
+
+impl fmt::Display for FetchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Fetch report:")?;
+        write_outcomes(
+            f,
+            &self.results,
+            |result| {
+                if result.updates.is_empty() {
+                    "up to date"
+                } else {
+                    "success"
+                }
+            },
+            |result| result.updates.clone(),
+        )
+    }
+}
+
+/// Fetches every remote in `remotes` at the same time, with
+/// `options.prune` removing remote-tracking refs of deleted branches, and
+/// prints a report of how each remote went. Use [`FetchReport::check`] to
+/// fail when any of them could not be fetched.
+pub fn fetch_from_remotes(remotes: &RemoteSet, options: &FetchOptions) -> GitResult<FetchReport> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    ensure_remotes_exist(remotes)?;
+
+    let remotes: Vec<&Remote> = remotes.iter().collect();
+    let results = run_parallel(&remotes, remotes.len(), |remote| {
+        fetch_remote(remote, options.prune)
+    });
+
+    let report = FetchReport { results };
+    print!("{}", report);
+    Ok(report)
+}
+
+/// Fetches every remote in `remotes` at the same time, updating their
+/// remote-tracking refs, and returns the ones that failed.
+pub(crate) fn fetch_remotes(remotes: &[&Remote]) -> Vec<FetchError> {
+    failed_fetches(run_parallel(remotes, remotes.len(), |remote| {
+        fetch_remote(remote, false)
+    }))
+}
+
+/// Like [`fetch_remotes`], but stores each remote's branches under
+/// `<namespace>/<remote>/` instead of its remote-tracking refs, which are
+/// left alone, pruning branches the remote no longer has.
+pub(crate) fn fetch_remotes_into(remotes: &[&Remote], namespace: &str) -> Vec<FetchError> {
+    failed_fetches(run_parallel(remotes, remotes.len(), |remote| {
+        let refspec = format!("+refs/heads/*:{}/{}/*", namespace, remote.name);
+        run_fetch(
+            remote,
+            &["--prune", "--no-tags", "--refmap=", &remote.name, &refspec],
+        )
+    }))
+}
+
+fn failed_fetches(results: Vec<RemoteFetchResult>) -> Vec<FetchError> {
+    results
+        .into_iter()
+        .filter(|result| !result.is_success())
+        .map(|result| FetchError {
+            remote: result.remote,
+            message: result.message,
+        })
+        .collect()
+}
+
+/// Fetches `remote` into its remote-tracking refs.
+fn fetch_remote(remote: &Remote, prune: bool) -> RemoteFetchResult {
+    if prune {
+        run_fetch(remote, &["--prune", &remote.name])
+    } else {
+        run_fetch(remote, &[&remote.name])
+    }
+}
+
+/// Runs `git fetch` with `args` within `remote`'s timeout.
+fn run_fetch(remote: &Remote, args: &[&str]) -> RemoteFetchResult {
+    let mut command = Command::new("git");
+    command.arg("fetch").args(args);
+
+    let mut result = RemoteFetchResult {
+        remote: remote.name.clone(),
+        updates: Vec::new(),
+        failure: None,
+        message: String::new(),
+    };
+
+    let run = match run_with_timeout(&mut command, remote.timeout) {
+        Ok(run) => run,
+        Err(e) => {
+            result.failure = Some(FailureKind::Other);
+            result.message = e.to_string();
+            return result;
+        }
+    };
+
+    let stderr = String::from_utf8_lossy(&run.output.stderr);
+    if run.timed_out {
+        result.failure = Some(FailureKind::Timeout);
+        result.message = GitError::Timeout {
+            remote: remote.name.clone(),
+            after: run.elapsed,
+        }
+        .to_string();
+    } else if !run.output.status.success() {
+        result.failure = Some(classify_failure(&stderr));
+        result.message = stderr.trim().to_string();
+    } else {
+        result.updates = fetch_updates(&stderr);
+    }
+
+    result
+}
+
+/// The updated refs in `git fetch`'s error output, which lists each one as
+/// `<summary> <from> -> <to>` after a `From <url>` line.
+pub(crate) fn fetch_updates(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter(|line| line.contains(" -> "))
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+/// Settings for a single `pull_from_remotes` run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PullOptions {
+    /// When the primary remote cannot be reached, pull from the next added
+    /// remote instead.
+    pub failover: bool,
+}
+
+/// Pulls the current branch from the [primary](RemoteSet::primary) remote.
+/// With `options.failover`, a network failure or timeout moves on to the
+/// other added remotes in order; any other failure stops right away. Only
+/// the fetch is timed: the branch is then merged (or rebased, as
+/// `pull.rebase` says) by `git pull` from the local repository, with its
+/// output going to the terminal, so a slow merge is never killed halfway.
+/// Returns the name of the remote that was pulled from.
+pub fn pull_from_remotes(remotes: &RemoteSet, options: &PullOptions) -> GitResult<String> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    let branch = current_branch()?;
+    let primary = remotes
+        .primary()
+        .ok_or_else(|| GitError::Usage("No remotes to pull from".to_string()))?;
+
+    let existing = get_existing_remotes();
+    if !existing.contains(&primary.name) {
+        return Err(GitError::RemoteNotFound(primary.name.clone()));
+    }
+
+    let mut candidates = vec![primary];
+    if options.failover {
+        candidates.extend(
+            remotes
+                .iter()
+                .filter(|remote| remote.name != primary.name && existing.contains(&remote.name)),
+        );
+    }
+
+    let mut unreachable: Option<(&Remote, FailureKind, String)> = None;
+    for remote in candidates {
+        if let Some((previous, failure, _)) = &unreachable {
+            println!(
+                "Warning: could not reach {} [{}]; pulling from {} instead.",
+                previous.name,
+                failure.label(),
+                remote.name
+            );
+        }
+
+        let tracking = format!("refs/remotes/{}/{}", remote.name, branch);
+        let refspec = format!("+refs/heads/{}:{}", branch, tracking);
+        let fetched = run_fetch(remote, &[&remote.name, &refspec]);
+        let Some(failure) = fetched.failure else {
+            merge_fetched(&branch, remote, &tracking)?;
+            if remote.name != primary.name {
+                println!(
+                    "Pulled {} from {} because {} could not be reached; pull again once it is back.",
+                    branch, remote.name, primary.name
+                );
+            }
+            return Ok(remote.name.clone());
+        };
+
+        // Only an unreachable remote is worth failing over from; a
+        // missing branch would not go away elsewhere.
+        if !failure.is_transient() {
+            return Err(GitError::CommandFailed(format!(
+                "Could not pull {} from {}: {}",
+                branch, remote.name, fetched.message
+            )));
+        }
+        unreachable = Some((remote, failure, fetched.message));
+    }
+
+    let (remote, _, message) = unreachable.expect("the primary remote is always tried");
+    let hint = if options.failover {
+        ""
+    } else {
+        "\nPass --failover to pull from another remote when the primary is unreachable."
+    };
+    Err(GitError::CommandFailed(format!(
+        "Could not pull {} from {}: {}{}",
+        branch, remote.name, message, hint
+    )))
+}
+
+/// Pulls the just-fetched `tracking` ref into `branch` from the local
+/// repository, leaving git's output, such as the files with conflicts, on
+/// the terminal.
+fn merge_fetched(branch: &str, remote: &Remote, tracking: &str) -> GitResult<()> {
+    let status = Command::new("git")
+        .args(["pull", "--no-edit", ".", tracking])
+        .status()?;
+    if !status.success() {
+        return Err(GitError::CommandFailed(format!(
+            "Could not pull {} from {}: git could not merge {}/{}, see its output above",
+            branch, remote.name, remote.name, branch
+        )));
+    }
+
+    Ok(())
+}
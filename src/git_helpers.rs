@@ -1,5 +1,13 @@
-use std::io;
-use std::process::Command;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::divergence::Divergence;
+use crate::remotes::{Remote, RemoteSet};
 
 #[derive(Debug)]
 pub enum GitError {
@@ -8,8 +16,121 @@ pub enum GitError {
     NotAGitRepository,
     RemoteNotFound(String),
     RemoteAlreadyExists(String),
+    InvalidRemoteName(String),
+    InvalidConfig {
+        path: PathBuf,
+        message: String,
+    },
+    Usage(String),
+    /// Pushing failed on `remotes`; `partial` is set when other remotes
+    /// succeeded.
+    PushFailed {
+        remotes: Vec<String>,
+        partial: bool,
+    },
+    /// Fetching failed on `remotes`; `partial` is set when other remotes
+    /// were fetched.
+    FetchFailed {
+        remotes: Vec<String>,
+        partial: bool,
+    },
+    /// HEAD is not on a branch, so there is no current branch to push.
+    DetachedHead,
+    /// A git command talking to `remote` was killed after running for `after`.
+    Timeout {
+        remote: String,
+        after: Duration,
+    },
+    /// After a push, `git ls-remote` showed `reference` on `remote` at
+    /// `actual` (`None` when missing) rather than the pushed `expected`.
+    VerificationFailed {
+        remote: String,
+        reference: String,
+        expected: String,
+        actual: Option<String>,
+    },
+    /// The branches of two remotes have split histories, so pushing was
+    /// refused.
+    Diverged(Vec<Divergence>),
 }
 
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::CommandFailed(message) => write!(f, "{}", message.trim_end()),
+            GitError::IoError(err) => write!(f, "I/O error: {}", err),
+            GitError::NotAGitRepository => write!(f, "Not a git repository"),
+            GitError::RemoteNotFound(name) => write!(f, "Remote '{}' not found", name),
+            GitError::RemoteAlreadyExists(name) => write!(f, "Remote '{}' already exists", name),
+            GitError::InvalidRemoteName(name) => write!(f, "Invalid remote name '{}'", name),
+            GitError::InvalidConfig { path, message } => {
+                write!(f, "Invalid config {}: {}", path.display(), message)
+            }
+            GitError::Usage(message) => write!(f, "{}", message),
+            GitError::PushFailed { remotes, partial } => {
+                let scope = if *partial {
+                    "Partial failure"
+                } else {
+                    "Push failed"
+                };
+                write!(f, "{}: could not push to {}", scope, remotes.join(", "))
+            }
+            GitError::FetchFailed { remotes, partial } => {
+                let scope = if *partial {
+                    "Partial failure"
+                } else {
+                    "Fetch failed"
+                };
+                write!(f, "{}: could not fetch {}", scope, remotes.join(", "))
+            }
+            GitError::DetachedHead => write!(
+                f,
+                "HEAD is detached. Check out a branch, or pass --to-ref refs/heads/<name> to push the commit to a branch"
+            ),
+            GitError::Timeout { remote, after } => write!(
+                f,
+                "Remote '{}' timed out after {:.1}s",
+                remote,
+                after.as_secs_f64()
+            ),
+            GitError::VerificationFailed {
+                remote,
+                reference,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Verification failed on '{}': {} is {}, expected {}",
+                remote,
+                reference,
+                match actual {
+                    Some(actual) => format!("at {}", actual),
+                    None => "missing".to_string(),
+                },
+                expected
+            ),
+            GitError::Diverged(divergences) => {
+                let pairs: Vec<String> = divergences
+                    .iter()
+                    .map(|divergence| {
+                        format!(
+                            "{} and {} have diverged on {}",
+                            divergence.other, divergence.remote, divergence.branch
+                        )
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "{}. Resolve the divergence before pushing, e.g. with `andiamo reconcile`",
+                    pairs.join("; ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
 impl From<io::Error> for GitError {
     fn from(err: io::Error) -> Self {
         GitError::IoError(err)
@@ -42,6 +163,24 @@ pub fn is_git_repo() -> bool {
     }
 }
 
+/// Whether `path` is a bare repository. Unlike [`is_git_repo`], this does
+/// not depend on the current directory.
+pub fn is_bare_repo(path: &Path) -> bool {
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(path)
+        .args(["rev-parse", "--is-bare-repository"])
+        .output();
+
+    match output {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            output.status.success() && stdout.trim() == "true"
+        }
+        Err(_) => false,
+    }
+}
+
 pub fn init_repo() -> GitResult<()> {
     if is_git_repo() {
         println!("Git repository already exists in the current directory.");
@@ -70,6 +209,16 @@ pub fn init_repo() -> GitResult<()> {
     }
 }
 
+/// Prints what [`init_repo`] would do, without changing anything.
+pub fn preview_init() -> GitResult<()> {
+    if is_git_repo() {
+        println!("Git repository already exists in the current directory. Nothing to do.");
+    } else {
+        println!("Would run: git init");
+    }
+    Ok(())
+}
+
 pub fn get_existing_remotes() -> Vec<String> {
     let output = Command::new("git").arg("remote").output();
 
@@ -94,12 +243,6 @@ pub fn add_remotes_with_urls(
     origin_url: Option<String>,
     mirror_url: Option<String>,
 ) -> GitResult<()> {
-    if !is_git_repo() {
-        return Err(GitError::NotAGitRepository);
-    }
-
-    let existing_remotes = get_existing_remotes();
-
     let origin_url = match origin_url {
         Some(url) => url,
         None => {
@@ -118,20 +261,17 @@ pub fn add_remotes_with_urls(
         }
     };
 
-    let mut remotes_to_add = Vec::new();
+    let mut remotes = RemoteSet::new();
+    remotes.insert(Remote::new("origin", Some(origin_url)))?;
+    remotes.insert(Remote::new("mirror", Some(mirror_url)))?;
 
-    if existing_remotes.contains(&"origin".to_string()) {
-        println!("Remote 'origin' already exists. Skipping...");
-    } else {
-        remotes_to_add.push(("origin", &origin_url));
-    }
-
-    if existing_remotes.contains(&"mirror".to_string()) {
-        println!("Remote 'mirror' already exists. Skipping...");
-    } else {
-        remotes_to_add.push(("mirror", &mirror_url));
-    }
+    add_remotes(&remotes)
+}
 
+/// Registers every remote in `remotes` with git, skipping the ones that
+/// already exist. Each remote that still needs adding must carry a URL.
+pub fn add_remotes(remotes: &RemoteSet) -> GitResult<()> {
+    let remotes_to_add = plan_remotes_to_add(remotes)?;
     let remotes_count = remotes_to_add.len();
 
     for (name, url) in remotes_to_add {
@@ -164,100 +304,571 @@ pub fn add_remotes_with_urls(
     }
 
     if remotes_count == 0 {
-        println!("Both remotes already exist. No remotes were added.");
+        println!("All remotes already exist. No remotes were added.");
+    }
+
+    Ok(())
+}
+
+/// Prints the git commands [`add_remotes`] would run, without changing
+/// anything.
+pub fn preview_add_remotes(remotes: &RemoteSet) -> GitResult<()> {
+    let remotes_to_add = plan_remotes_to_add(remotes)?;
+
+    for (name, url) in &remotes_to_add {
+        println!("Would run: git remote add {} {}", name, url);
+    }
+
+    if remotes_to_add.is_empty() {
+        println!("All remotes already exist. No remotes would be added.");
     }
 
     Ok(())
 }
 
-pub fn push_to_remotes() -> GitResult<()> {
+/// The remotes of `remotes` that are not registered yet, with their URLs.
+/// Reports the ones that already exist.
+fn plan_remotes_to_add(remotes: &RemoteSet) -> GitResult<Vec<(&str, &String)>> {
     if !is_git_repo() {
         return Err(GitError::NotAGitRepository);
     }
 
+    if remotes.is_empty() {
+        return Err(GitError::CommandFailed(
+            "At least one remote is required".to_string(),
+        ));
+    }
+
     let existing_remotes = get_existing_remotes();
 
-    if !existing_remotes.contains(&"origin".to_string()) {
-        return Err(GitError::RemoteNotFound("origin".to_string()));
+    let mut remotes_to_add = Vec::new();
+
+    for remote in remotes {
+        if existing_remotes.contains(&remote.name) {
+            println!("Remote '{}' already exists. Skipping...", remote.name);
+            continue;
+        }
+
+        match &remote.url {
+            Some(url) => remotes_to_add.push((remote.name.as_str(), url)),
+            None => {
+                return Err(GitError::CommandFailed(format!(
+                    "A URL is required for remote '{}'",
+                    remote.name
+                )));
+            }
+        }
     }
 
-    if !existing_remotes.contains(&"mirror".to_string()) {
-        return Err(GitError::RemoteNotFound("mirror".to_string()));
+    Ok(remotes_to_add)
+}
+
+/// Returns the URL git has configured for `name`, if the remote exists.
+pub fn get_remote_url(name: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["remote", "get-url", name])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Some(stdout.trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+pub fn remove_remote(name: &str) -> GitResult<()> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    if !get_existing_remotes()
+        .iter()
+        .any(|existing| existing == name)
+    {
+        return Err(GitError::RemoteNotFound(name.to_string()));
     }
 
+    let output = Command::new("git")
+        .args(["remote", "remove", name])
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                println!("Remote '{}' removed successfully.", name);
+                Ok(())
+            } else {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Err(GitError::CommandFailed(format!(
+                    "Error removing remote '{}': {}",
+                    name, stderr
+                )))
+            }
+        }
+        Err(e) => Err(GitError::CommandFailed(format!(
+            "Error removing remote '{}': {}",
+            name, e
+        ))),
+    }
+}
+
+pub fn set_remote_url(name: &str, url: &str) -> GitResult<()> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    if !get_existing_remotes()
+        .iter()
+        .any(|existing| existing == name)
+    {
+        return Err(GitError::RemoteNotFound(name.to_string()));
+    }
+
+    let output = Command::new("git")
+        .args(["remote", "set-url", name, url])
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                println!("Remote '{}' now points at {}.", name, url);
+                Ok(())
+            } else {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Err(GitError::CommandFailed(format!(
+                    "Error setting URL of remote '{}': {}",
+                    name, stderr
+                )))
+            }
+        }
+        Err(e) => Err(GitError::CommandFailed(format!(
+            "Error setting URL of remote '{}': {}",
+            name, e
+        ))),
+    }
+}
+
+/// Returns the name of the branch HEAD points at, or
+/// [`GitError::DetachedHead`] when it points at a commit directly.
+pub fn current_branch() -> GitResult<String> {
     let branch_output = Command::new("git")
         .args(["rev-parse", "--abbrev-ref", "HEAD"])
         .output();
 
-    let branch_name = match branch_output {
+    match branch_output {
         Ok(output) => {
             if output.status.success() {
                 let stdout = String::from_utf8_lossy(&output.stdout);
-                stdout.trim().to_string()
+                match stdout.trim() {
+                    "HEAD" => Err(GitError::DetachedHead),
+                    branch => Ok(branch.to_string()),
+                }
             } else {
-                return Err(GitError::CommandFailed(
+                Err(GitError::CommandFailed(
                     "Could not determine current branch. Make sure you're on a branch.".to_string(),
-                ));
+                ))
             }
         }
-        Err(e) => {
-            return Err(GitError::CommandFailed(format!(
-                "Error determining current branch: {}",
-                e
-            )));
-        }
-    };
+        Err(e) => Err(GitError::CommandFailed(format!(
+            "Error determining current branch: {}",
+            e
+        ))),
+    }
+}
 
-    println!("Pushing to origin...");
+/// The branch HEAD points at when it has no commits yet, as in a freshly
+/// initialised repository; `None` otherwise.
+pub fn unborn_branch() -> Option<String> {
     let output = Command::new("git")
-        .args(["push", "origin", &branch_name])
+        .args(["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    resolve_ref(&format!("refs/heads/{}", branch))
+        .is_none()
+        .then_some(branch)
+}
+
+/// Returns the names of all local branches, sorted by name.
+pub fn local_branches() -> GitResult<Vec<String>> {
+    let output = Command::new("git")
+        .args(["for-each-ref", "--format=%(refname)", "refs/heads/"])
         .output();
 
     match output {
         Ok(output) => {
             if output.status.success() {
-                println!("Successfully pushed to origin.");
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                Ok(stdout
+                    .lines()
+                    .filter_map(|line| line.trim().strip_prefix("refs/heads/"))
+                    .map(str::to_string)
+                    .collect())
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr);
-                return Err(GitError::CommandFailed(format!(
-                    "Error pushing to origin: {}",
+                Err(GitError::CommandFailed(format!(
+                    "Error listing local branches: {}",
                     stderr
-                )));
+                )))
             }
         }
-        Err(e) => {
-            return Err(GitError::CommandFailed(format!(
-                "Error pushing to origin: {}",
-                e
-            )));
-        }
+        Err(e) => Err(GitError::CommandFailed(format!(
+            "Error listing local branches: {}",
+            e
+        ))),
     }
+}
 
-    println!("Pushing to mirror...");
+/// Returns the remote `branch` tracks, if it has an upstream configured.
+pub fn branch_upstream(branch: &str) -> Option<String> {
     let output = Command::new("git")
-        .args(["push", "mirror", &branch_name])
+        .args(["config", "--get", &format!("branch.{}.remote", branch)])
         .output();
 
     match output {
-        Ok(output) => {
-            if output.status.success() {
-                println!("Successfully pushed to mirror.");
-            } else {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Some(stdout.trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Makes `branch` track the branch of the same name on `remote`, as
+/// `git push -u` would.
+pub fn set_branch_upstream(branch: &str, remote: &str) -> GitResult<()> {
+    let settings = [
+        (format!("branch.{}.remote", branch), remote.to_string()),
+        (
+            format!("branch.{}.merge", branch),
+            format!("refs/heads/{}", branch),
+        ),
+    ];
+
+    for (key, value) in &settings {
+        let output = Command::new("git").args(["config", key, value]).output();
+        match output {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 return Err(GitError::CommandFailed(format!(
-                    "Error pushing to mirror: {}",
-                    stderr
+                    "Error setting the upstream of '{}': {}",
+                    branch, stderr
+                )));
+            }
+            Err(e) => {
+                return Err(GitError::CommandFailed(format!(
+                    "Error setting the upstream of '{}': {}",
+                    branch, e
                 )));
             }
         }
-        Err(e) => {
-            return Err(GitError::CommandFailed(format!(
-                "Error pushing to mirror: {}",
-                e
-            )));
+    }
+
+    Ok(())
+}
+
+/// Resolves `rev` to a commit SHA, returning `None` if it does not exist.
+pub fn resolve_ref(rev: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", rev])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Some(stdout.trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Returns the best common ancestor of `left` and `right`, or `None` when
+/// their histories are unrelated.
+pub fn merge_base(left: &str, right: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["merge-base", left, right])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Some(stdout.trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Counts the commits reachable only from `left` and only from `right`,
+/// as `git rev-list --left-right --count left...right` does.
+pub fn ahead_behind(left: &str, right: &str) -> GitResult<(usize, usize)> {
+    let range = format!("{}...{}", left, right);
+    let output = Command::new("git")
+        .args(["rev-list", "--left-right", "--count", &range])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let mut counts = stdout.split_whitespace().map(str::parse::<usize>);
+            match (counts.next(), counts.next()) {
+                (Some(Ok(left)), Some(Ok(right))) => Ok((left, right)),
+                _ => Err(GitError::CommandFailed(format!(
+                    "Unexpected output comparing {}: {}",
+                    range,
+                    stdout.trim()
+                ))),
+            }
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(GitError::CommandFailed(format!(
+                "Error comparing {}: {}",
+                range, stderr
+            )))
+        }
+        Err(e) => Err(GitError::CommandFailed(format!(
+            "Error comparing {}: {}",
+            range, e
+        ))),
+    }
+}
+
+/// Counts the commits reachable from `rev`.
+pub fn count_commits(rev: &str) -> GitResult<usize> {
+    let output = Command::new("git")
+        .args(["rev-list", "--count", rev])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout.trim().parse().map_err(|_| {
+                GitError::CommandFailed(format!(
+                    "Unexpected output counting the commits in {}: {}",
+                    rev,
+                    stdout.trim()
+                ))
+            })
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(GitError::CommandFailed(format!(
+                "Error counting the commits in {}: {}",
+                rev, stderr
+            )))
+        }
+        Err(e) => Err(GitError::CommandFailed(format!(
+            "Error counting the commits in {}: {}",
+            rev, e
+        ))),
+    }
+}
+
+/// Resolves each of `revs` to a full object name with a single
+/// `git cat-file --batch-check`, giving `None` for those that do not exist.
+pub fn resolve_objects(revs: &[&str]) -> Vec<Option<String>> {
+    if revs.is_empty() {
+        return Vec::new();
+    }
+
+    let child = Command::new("git")
+        .args(["cat-file", "--batch-check=%(objectname)"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let output = child.and_then(|mut child| {
+        if let Some(mut stdin) = child.stdin.take() {
+            // Revisions with newlines would desynchronise the batch.
+            let input: String = revs
+                .iter()
+                .map(|rev| format!("{}\n", rev.replace('\n', " ")))
+                .collect();
+            stdin.write_all(input.as_bytes())?;
+        }
+        child.wait_with_output()
+    });
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let mut names: Vec<Option<String>> = stdout
+                .lines()
+                .map(|line| (!line.ends_with(" missing")).then(|| line.trim().to_string()))
+                .collect();
+            names.resize(revs.len(), None);
+            names
+        }
+        _ => vec![None; revs.len()],
+    }
+}
+
+/// Checks that every remote in `remotes` is registered with git.
+pub fn ensure_remotes_exist(remotes: &RemoteSet) -> GitResult<()> {
+    if remotes.is_empty() {
+        return Err(GitError::CommandFailed(
+            "At least one remote is required".to_string(),
+        ));
+    }
+
+    let existing_remotes = get_existing_remotes();
+
+    for name in remotes.names() {
+        if !existing_remotes.iter().any(|existing| existing == name) {
+            return Err(GitError::RemoteNotFound(name.to_string()));
         }
     }
 
-    println!("All changes pushed successfully to both remotes!");
     Ok(())
 }
+
+/// Output of a command run through [`run_with_timeout`].
+pub(crate) struct TimedOutput {
+    pub output: Output,
+    pub timed_out: bool,
+    pub elapsed: Duration,
+}
+
+/// How long output is still collected after a timed-out command was
+/// killed, in case a process it started keeps the pipes open.
+const KILL_GRACE: Duration = Duration::from_millis(500);
+
+/// Runs `command` to completion, capturing its output, and kills it once
+/// `timeout` has passed.
+///
+/// Without a controlling terminal the command runs in its own process
+/// group, so that the ssh or credential helpers git spawned are killed
+/// along with it. With one it stays in the foreground: ssh can still ask
+/// for a passphrase on the terminal and Ctrl-C still reaches git, but a
+/// timeout only kills git itself.
+pub(crate) fn run_with_timeout(
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> io::Result<TimedOutput> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let own_group = timeout.is_some() && !has_controlling_terminal();
+    #[cfg(unix)]
+    if own_group {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let start = Instant::now();
+    let mut child = command.spawn()?;
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            if own_group {
+                kill_process_group(&mut child);
+            } else {
+                let _ = child.kill();
+            }
+            timed_out = true;
+            break child.wait()?;
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    let grace = if timed_out { Some(KILL_GRACE) } else { None };
+    Ok(TimedOutput {
+        output: Output {
+            status,
+            stdout: stdout.collect(grace),
+            stderr: stderr.collect(grace),
+        },
+        timed_out,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// A pipe being read to the end on another thread.
+struct BackgroundRead {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl BackgroundRead {
+    /// What was read: everything, or with `grace` whatever arrived before
+    /// the pipe closed or `grace` ran out.
+    fn collect(self, grace: Option<Duration>) -> Vec<u8> {
+        if let Some(grace) = grace {
+            let deadline = Instant::now() + grace;
+            while !self.handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+        if self.handle.is_finished() || grace.is_none() {
+            let _ = self.handle.join();
+        }
+
+        let buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        buffer.clone()
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> BackgroundRead {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let shared = Arc::clone(&buffer);
+    let handle = thread::spawn(move || {
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut chunk = [0; 8192];
+        while let Ok(read) = pipe.read(&mut chunk) {
+            if read == 0 {
+                break;
+            }
+            shared
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .extend_from_slice(&chunk[..read]);
+        }
+    });
+    BackgroundRead { buffer, handle }
+}
+
+/// Whether andiamo can reach a terminal that ssh or git could prompt on.
+#[cfg(unix)]
+fn has_controlling_terminal() -> bool {
+    std::fs::File::open("/dev/tty").is_ok()
+}
+
+#[cfg(not(unix))]
+fn has_controlling_terminal() -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) {
+    // SAFETY: kill(2) has no memory-safety preconditions; a negative pid
+    // addresses the process group created for the child.
+    let result = unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+    if result != 0 {
+        let _ = child.kill();
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut Child) {
+    let _ = child.kill();
+}
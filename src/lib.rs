@@ -1,7 +1,31 @@
 pub mod cli;
+pub mod config;
+pub mod divergence;
+pub mod doctor;
+pub mod fetch;
 pub mod git_helpers;
+mod parallel;
+pub mod porcelain;
+pub mod push;
+pub mod reconcile;
+pub mod relay;
+pub mod remotes;
+mod report;
+pub mod retry;
+pub mod status;
 
 pub use cli::Cli;
+pub use config::{GlobalConfig, RepoConfig};
+pub use fetch::{FetchOptions, FetchReport, PullOptions, fetch_from_remotes, pull_from_remotes};
 pub use git_helpers::{
-    GitError, GitResult, add_remotes_with_urls, check_git_installed, init_repo, push_to_remotes,
+    GitError, GitResult, add_remotes, add_remotes_with_urls, check_git_installed, init_repo,
+    preview_add_remotes, preview_init,
 };
+pub use porcelain::{RefUpdate, RefUpdateKind, parse_porcelain};
+pub use push::{
+    BranchSelection, MirrorPreview, PushOptions, PushReport, PushStatus, PushSummary, RefMismatch,
+    RemotePushResult, SuccessPolicy, TagMode, UpstreamTarget, push_to_remotes,
+};
+pub use relay::{RelayOptions, RelayReport, relay};
+pub use remotes::{Remote, RemoteMode, RemoteRole, RemoteSet};
+pub use status::{StatusOptions, StatusReport, collect_status};
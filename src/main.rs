@@ -1,13 +1,26 @@
-mod cli;
-mod git_helpers;
+use clap::{CommandFactory, Parser};
 
-use clap::Parser;
-
-use cli::Cli;
-use dialoguer::Input;
-use git_helpers::{
-    add_remotes_with_urls, check_git_installed, init_repo, push_to_remotes,
+use andiamo::cli::{
+    Cli, Command, FetchArgs, PullArgs, PushArgs, ReconcileArgs, RelayArgs, RemoteCommand,
+    StatusArgs,
+};
+use andiamo::config::{
+    RepoConfig, apply_env_urls, apply_url_overrides, load_effective_config, resolve_remotes,
+    url_env_var,
 };
+use andiamo::doctor::{CheckStatus, run_checks};
+use andiamo::fetch::{FetchOptions, PullOptions, fetch_from_remotes, pull_from_remotes};
+use andiamo::git_helpers::{
+    GitError, GitResult, add_remotes, check_git_installed, get_existing_remotes, get_remote_url,
+    init_repo, preview_add_remotes, preview_init, remove_remote, set_remote_url,
+};
+use andiamo::push::{PushOptions, PushSummary, pending_mirror_deletions, push_to_remotes};
+use andiamo::reconcile::{ReconcileStrategy, plan_reconcile, reconcile};
+use andiamo::relay::{RelayOptions, relay};
+use andiamo::remotes::{Remote, RemoteSet};
+use andiamo::status::{StatusOptions, collect_status};
+use dialoguer::{Confirm, Input};
+use std::io::IsTerminal;
 
 fn main() {
     let cli = Cli::parse();
@@ -19,37 +32,402 @@ fn main() {
         std::process::exit(1);
     }
 
-    // Execute commands in order
-    if cli.init {
-        if let Err(e) = init_repo() {
-            eprintln!("Error: {:?}", e);
-            std::process::exit(1);
+    if let Err(e) = run(&cli) {
+        eprintln!("Error: {}", e);
+        let code = match e {
+            GitError::Usage(_) => 2,
+            GitError::PushFailed { partial: true, .. }
+            | GitError::FetchFailed { partial: true, .. } => {
+                PushSummary::PartialFailure.exit_code()
+            }
+            _ => 1,
+        };
+        std::process::exit(code);
+    }
+}
+
+fn run(cli: &Cli) -> GitResult<()> {
+    match &cli.command {
+        Some(Command::Init { dry_run: false }) => init_repo(),
+        Some(Command::Init { dry_run: true }) => preview_init(),
+        Some(Command::Remote { command }) => run_remote(cli, command),
+        Some(Command::Push(args)) => run_push(cli, args, &load_remotes(cli)?),
+        Some(Command::Fetch(args)) => run_fetch(cli, args),
+        Some(Command::Pull(args)) => run_pull(cli, args),
+        Some(Command::Status(args)) => run_status(cli, args),
+        Some(Command::Sync(args)) if args.dry_run => {
+            let remotes = load_remotes_with_urls(cli)?;
+            preview_add_remotes(&remotes)?;
+
+            let existing = get_existing_remotes();
+            let missing: Vec<&str> = remotes
+                .names()
+                .into_iter()
+                .filter(|name| !existing.iter().any(|existing| existing == name))
+                .collect();
+            if !missing.is_empty() {
+                println!(
+                    "Skipping the push preview until {} {} added.",
+                    missing.join(", "),
+                    if missing.len() == 1 { "is" } else { "are" }
+                );
+                return Ok(());
+            }
+
+            run_push(cli, args, &remotes)
+        }
+        Some(Command::Sync(args)) => {
+            let remotes = load_remotes_with_urls(cli)?;
+            add_remotes(&remotes)?;
+            run_push(cli, args, &remotes)
         }
+        Some(Command::Doctor) => run_doctor(cli),
+        Some(Command::Reconcile(args)) => run_reconcile(cli, args),
+        Some(Command::Relay(args)) => run_relay(cli, args),
+        None if cli.uses_legacy_flags() => run_legacy(cli),
+        None => {
+            Cli::command().print_help()?;
+            Ok(())
+        }
+    }
+}
+
+/// Runs the deprecated `--init/--add-remotes/--push` flags in their
+/// historical order.
+fn run_legacy(cli: &Cli) -> GitResult<()> {
+    if cli.init {
+        eprintln!("Warning: --init is deprecated; use `andiamo init` instead.");
+        init_repo()?;
     }
 
     if cli.add_remotes {
-        // Prompt for origin remote URL
-        let origin_url: String = Input::new()
-            .with_prompt("Enter the URL for the 'origin' remote")
-            .interact_text()
-            .expect("Failed to read origin URL");
+        eprintln!("Warning: --add-remotes is deprecated; use `andiamo remote add` instead.");
+        add_remotes(&load_remotes_with_urls(cli)?)?;
+    }
 
-        // Prompt for mirror remote URL
-        let mirror_url: String = Input::new()
-            .with_prompt("Enter the URL for the 'mirror' remote")
-            .interact_text()
-            .expect("Failed to read mirror URL");
+    if cli.push {
+        eprintln!("Warning: --push is deprecated; use `andiamo push` instead.");
+        run_push(cli, &PushArgs::default(), &load_remotes(cli)?)?;
+    }
+
+    Ok(())
+}
+
+fn run_remote(cli: &Cli, command: &RemoteCommand) -> GitResult<()> {
+    match command {
+        RemoteCommand::Add {
+            name: Some(name),
+            url,
+            dry_run,
+        } => {
+            let mut remotes = RemoteSet::new();
+            remotes.insert(Remote::new(name.clone(), url.clone()))?;
+            add_or_preview(&remotes, *dry_run)
+        }
+        RemoteCommand::Add {
+            name: None,
+            dry_run,
+            ..
+        } => add_or_preview(&load_remotes_with_urls(cli)?, *dry_run),
+        RemoteCommand::List => {
+            let remotes = load_remotes(cli)?;
+            let existing = get_existing_remotes();
+
+            for remote in &remotes {
+                match get_remote_url(&remote.name) {
+                    Some(url) => println!("{}\t{}", remote.name, url),
+                    None => println!("{}\t(not added)", remote.name),
+                }
+            }
+
+            for name in existing.iter().filter(|name| !remotes.contains(name)) {
+                let url = get_remote_url(name).unwrap_or_default();
+                println!("{}\t{}\t(not managed by andiamo)", name, url);
+            }
 
-        if let Err(e) = add_remotes_with_urls(Some(origin_url), Some(mirror_url)) {
-            eprintln!("Error: {:?}", e);
-            std::process::exit(1);
+            Ok(())
         }
+        RemoteCommand::Remove { name } => remove_remote(name),
+        RemoteCommand::SetUrl { name, url } => set_remote_url(name, url),
     }
+}
 
-    if cli.push {
-        if let Err(e) = push_to_remotes() {
-            eprintln!("Error: {:?}", e);
-            std::process::exit(1);
+fn add_or_preview(remotes: &RemoteSet, dry_run: bool) -> GitResult<()> {
+    if dry_run {
+        preview_add_remotes(remotes)
+    } else {
+        add_remotes(remotes)
+    }
+}
+
+fn run_push(cli: &Cli, args: &PushArgs, remotes: &RemoteSet) -> GitResult<()> {
+    let config = load_config(cli)?;
+
+    let mut options = PushOptions::from_config(&config.push);
+    if args.jobs.is_some() {
+        options.jobs = args.jobs;
+    }
+    if args.keep_going {
+        options.keep_going = true;
+    }
+    if let Some(policy) = args.policy {
+        options.policy = policy;
+    }
+    if let Some(retries) = args.retries {
+        options.retry.retries = retries;
+    }
+    if args.timeout.is_some() {
+        options.timeout = args.timeout;
+    }
+    options.branches = args.branch_selection();
+    options.tags = args.tag_mode();
+    if args.atomic {
+        options.atomic = true;
+    }
+    if let Some(upstream) = &args.set_upstream {
+        options.upstream = upstream.clone();
+    }
+    if args.check_divergence {
+        options.check_divergence = true;
+    }
+    if args.no_check_divergence {
+        options.check_divergence = false;
+    }
+    if args.skip_verify {
+        options.verify = false;
+    }
+    options.force_with_lease = args.force_with_lease;
+    options.dry_run = args.dry_run;
+
+    // With --yes there is nothing to confirm, so mirrors are not asked
+    // twice.
+    if !options.dry_run && !args.yes {
+        confirm_mirror_deletions(cli, remotes, &mut options)?;
+    }
+
+    push_to_remotes(remotes, &options)?.check()
+}
+
+/// Asks before a push with `options` deletes refs from mirror remotes.
+/// Mirrors whose refs could not be listed are handed to the push as
+/// already failed.
+fn confirm_mirror_deletions(
+    cli: &Cli,
+    remotes: &RemoteSet,
+    options: &mut PushOptions,
+) -> GitResult<()> {
+    let preview = pending_mirror_deletions(remotes, options)?;
+    if !preview.deletions.is_empty() {
+        confirm_deletions(cli, &preview.deletions)?;
+    }
+    options.prechecked = preview.failures;
+    Ok(())
+}
+
+/// Lists the refs mirror remotes are about to lose and asks before going
+/// ahead. Without a terminal to ask on, `--yes` is required.
+fn confirm_deletions(cli: &Cli, deletions: &[(String, Vec<String>)]) -> GitResult<()> {
+    println!("The following refs will be deleted:");
+    for (remote, refs) in deletions {
+        for name in refs {
+            println!("  {}: {}", remote, name);
+        }
+    }
+
+    confirm(
+        cli,
+        "Delete these refs?",
+        "Refusing to delete refs on mirror remotes without confirmation. Pass --yes to go ahead.",
+        "Push cancelled; no refs were deleted.",
+    )
+}
+
+/// Asks `prompt`, failing with `refusal` as a usage error when there is no
+/// terminal to ask on and with `cancelled` when the answer is no.
+fn confirm(cli: &Cli, prompt: &str, refusal: &str, cancelled: &str) -> GitResult<()> {
+    if cli.urls.non_interactive || !std::io::stdin().is_terminal() {
+        return Err(GitError::Usage(refusal.to_string()));
+    }
+
+    let confirmed = Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .map_err(|e| GitError::CommandFailed(format!("Failed to read confirmation: {}", e)))?;
+
+    if !confirmed {
+        return Err(GitError::CommandFailed(cancelled.to_string()));
+    }
+
+    Ok(())
+}
+
+fn run_reconcile(cli: &Cli, args: &ReconcileArgs) -> GitResult<()> {
+    let remotes = load_remotes(cli)?;
+    let config = load_config(cli)?;
+    let mut options = PushOptions::from_config(&config.push);
+    options.dry_run = args.dry_run;
+
+    let plan = plan_reconcile(&remotes, args.branch.as_deref())?;
+    for divergence in &plan.divergences {
+        print!("{}", divergence);
+    }
+
+    if args.strategy == ReconcileStrategy::Overwrite
+        && !args.dry_run
+        && !args.yes
+        && !plan.divergences.is_empty()
+    {
+        confirm(
+            cli,
+            "Overwrite these remotes, dropping the commits only they have?",
+            "Refusing to overwrite remotes without confirmation. Pass --yes to go ahead.",
+            "Reconcile cancelled; nothing was changed.",
+        )?;
+    }
+
+    // The merge strategy ends with a push to every remote, mirrors
+    // included; the merge itself adds or removes no refs, so they can be
+    // asked about up front.
+    if args.strategy == ReconcileStrategy::Merge
+        && !args.dry_run
+        && !args.yes
+        && !plan.divergences.is_empty()
+    {
+        confirm_mirror_deletions(cli, &remotes, &mut options)?;
+    }
+
+    reconcile(&remotes, &plan, args.strategy, &options)
+}
+
+fn run_fetch(cli: &Cli, args: &FetchArgs) -> GitResult<()> {
+    let options = FetchOptions { prune: args.prune };
+    fetch_from_remotes(&load_remotes(cli)?, &options)?.check()
+}
+
+fn run_pull(cli: &Cli, args: &PullArgs) -> GitResult<()> {
+    let options = PullOptions {
+        failover: args.failover,
+    };
+    pull_from_remotes(&load_remotes(cli)?, &options)?;
+    Ok(())
+}
+
+/// Relays refs between remotes. No work tree is needed, so URLs come only
+/// from configuration, the environment and the command line; there is no
+/// prompt for missing ones.
+fn run_relay(cli: &Cli, args: &RelayArgs) -> GitResult<()> {
+    let mut remotes = load_remotes(cli)?;
+    apply_env_urls(&mut remotes);
+    apply_url_overrides(&mut remotes, &cli.urls.overrides())?;
+
+    let options = RelayOptions {
+        cache: args.cache.clone(),
+        source: args.from.clone(),
+        refs: args.refs.clone(),
+        prune: args.prune,
+        dry_run: args.dry_run,
+    };
+    relay(&remotes, &options)?.check()
+}
+
+fn run_status(cli: &Cli, args: &StatusArgs) -> GitResult<()> {
+    let remotes = load_remotes(cli)?;
+    let options = StatusOptions {
+        all_branches: args.all,
+        fetch: args.fetch,
+    };
+    let report = collect_status(&remotes, &options)?;
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| GitError::CommandFailed(format!("Error writing JSON: {}", e)))?;
+        println!("{}", json);
+    } else {
+        print!("{}", report);
+    }
+
+    Ok(())
+}
+
+fn run_doctor(cli: &Cli) -> GitResult<()> {
+    let checks = run_checks(cli.profile.as_deref(), cli.remotes.as_deref());
+
+    for check in &checks {
+        let label = match check.status {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warning => "warn",
+            CheckStatus::Error => "FAIL",
+        };
+        println!("[{}] {}: {}", label, check.name, check.detail);
+    }
+
+    let failures = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Error)
+        .count();
+
+    if failures > 0 {
+        return Err(GitError::CommandFailed(format!(
+            "doctor found {} problem(s)",
+            failures
+        )));
+    }
+
+    Ok(())
+}
+
+fn load_config(cli: &Cli) -> GitResult<RepoConfig> {
+    Ok(load_effective_config(cli.profile.as_deref())?.unwrap_or_default())
+}
+
+fn load_remotes(cli: &Cli) -> GitResult<RemoteSet> {
+    resolve_remotes(cli.remotes.as_deref(), Some(&load_config(cli)?))
+}
+
+/// Like [`load_remotes`], but also fills in URLs from the environment and
+/// the command line, then prompts for whatever is still missing.
+fn load_remotes_with_urls(cli: &Cli) -> GitResult<RemoteSet> {
+    let mut remotes = load_remotes(cli)?;
+    apply_env_urls(&mut remotes);
+    apply_url_overrides(&mut remotes, &cli.urls.overrides())?;
+
+    let interactive = !cli.urls.non_interactive && std::io::stdin().is_terminal();
+    prompt_missing_urls(&mut remotes, interactive)?;
+
+    Ok(remotes)
+}
+
+/// Prompts for the URL of each remote that is neither configured nor
+/// already added to the repository. Without a terminal to prompt on, a
+/// missing URL is a usage error.
+fn prompt_missing_urls(remotes: &mut RemoteSet, interactive: bool) -> GitResult<()> {
+    let existing = get_existing_remotes();
+
+    for remote in remotes
+        .iter_mut()
+        .filter(|remote| remote.url.is_none() && !existing.contains(&remote.name))
+    {
+        if !interactive {
+            return Err(GitError::Usage(format!(
+                "No URL for remote '{0}'. Pass --remote {0}=<URL> or set {1}.",
+                remote.name,
+                url_env_var(&remote.name)
+            )));
         }
+
+        let url: String = Input::new()
+            .with_prompt(format!("Enter the URL for the '{}' remote", remote.name))
+            .interact_text()
+            .map_err(|e| {
+                GitError::CommandFailed(format!(
+                    "Failed to read URL for remote '{}': {}",
+                    remote.name, e
+                ))
+            })?;
+
+        remote.url = Some(url);
     }
+
+    Ok(())
 }
@@ -0,0 +1,162 @@
+use std::fmt;
+
+/// How a remote handled one ref, from the flag column of
+/// `git push --porcelain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefUpdateKind {
+    /// An existing ref moved forward (` `).
+    FastForward,
+    /// An existing ref was overwritten with a non-descendant (`+`).
+    Forced,
+    /// The ref did not exist on the remote before (`*`).
+    New,
+    /// The ref was removed from the remote (`-`).
+    Deleted,
+    /// git refused to send the update, e.g. a non-fast-forward (`!`).
+    Rejected,
+    /// The remote refused the update, e.g. from a hook (`!`).
+    RemoteRejected,
+    /// The remote already had this value (`=`).
+    UpToDate,
+}
+
+impl RefUpdateKind {
+    pub fn is_rejected(self) -> bool {
+        matches!(
+            self,
+            RefUpdateKind::Rejected | RefUpdateKind::RemoteRejected
+        )
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RefUpdateKind::FastForward => "fast-forward",
+            RefUpdateKind::Forced => "forced",
+            RefUpdateKind::New => "created",
+            RefUpdateKind::Deleted => "deleted",
+            RefUpdateKind::Rejected => "rejected",
+            RefUpdateKind::RemoteRejected => "remote rejected",
+            RefUpdateKind::UpToDate => "up-to-date",
+        }
+    }
+}
+
+/// One ref line of `git push --porcelain` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefUpdate {
+    pub kind: RefUpdateKind,
+    /// Local ref or revision that was pushed; empty for deletions.
+    pub source: String,
+    /// Full name of the ref on the remote, e.g. `refs/heads/main`.
+    pub destination: String,
+    /// Where the remote ref pointed before, when git reports it.
+    pub old_sha: Option<String>,
+    /// Where the remote ref points after the update, or would have pointed
+    /// for rejected updates. `None` for deletions.
+    pub new_sha: Option<String>,
+    /// git's explanation, e.g. `non-fast-forward` or `pre-receive hook
+    /// declined`.
+    pub reason: Option<String>,
+}
+
+impl RefUpdate {
+    /// The destination without its `refs/heads/` or `refs/tags/` prefix.
+    pub fn short_name(&self) -> &str {
+        self.destination
+            .strip_prefix("refs/heads/")
+            .or_else(|| self.destination.strip_prefix("refs/tags/"))
+            .unwrap_or(&self.destination)
+    }
+
+    pub fn is_branch(&self) -> bool {
+        self.destination.starts_with("refs/heads/")
+    }
+
+    pub fn is_tag(&self) -> bool {
+        self.destination.starts_with("refs/tags/")
+    }
+
+    /// Parses one line such as `*\trefs/heads/x:refs/heads/x\t[new branch]`,
+    /// returning `None` for lines that are not ref lines (`To ...`, `Done`).
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let flag = fields.next()?;
+        let (source, destination) = fields.next()?.split_once(':')?;
+        let summary = fields.next().unwrap_or("").trim();
+
+        let (summary, reason) = match summary.split_once(" (") {
+            // A forced update always says "(forced update)"; the kind already
+            // tells as much.
+            Some((summary, _)) if flag == "+" => (summary, None),
+            Some((summary, reason)) => (summary, Some(reason.trim_end_matches(')').to_string())),
+            None => (summary, None),
+        };
+
+        let kind = match flag {
+            " " => RefUpdateKind::FastForward,
+            "+" => RefUpdateKind::Forced,
+            "*" => RefUpdateKind::New,
+            "-" => RefUpdateKind::Deleted,
+            "=" => RefUpdateKind::UpToDate,
+            "!" if summary.starts_with("[remote") => RefUpdateKind::RemoteRejected,
+            "!" => RefUpdateKind::Rejected,
+            _ => return None,
+        };
+
+        // "old..new" for fast-forwards, "old...new" for forced updates.
+        let (old_sha, new_sha) = match summary.split_once("..") {
+            Some((old, new)) if !summary.starts_with('[') => (
+                Some(old.to_string()),
+                Some(new.trim_start_matches('.').to_string()),
+            ),
+            _ => (None, None),
+        };
+
+        Some(RefUpdate {
+            kind,
+            source: source.to_string(),
+            destination: destination.to_string(),
+            old_sha,
+            new_sha,
+            reason,
+        })
+    }
+}
+
+impl fmt::Display for RefUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = if self.is_branch() {
+            format!("branch {}", self.short_name())
+        } else if self.is_tag() {
+            format!("tag {}", self.short_name())
+        } else {
+            self.destination.clone()
+        };
+        write!(f, "{}: {}", name, self.kind.label())?;
+
+        match (&self.old_sha, &self.new_sha) {
+            (Some(old), Some(new)) if self.kind != RefUpdateKind::UpToDate => {
+                write!(f, " {}..{}", abbreviate(old), abbreviate(new))?
+            }
+            (None, Some(new)) if self.kind == RefUpdateKind::New => {
+                write!(f, " at {}", abbreviate(new))?
+            }
+            _ => {}
+        }
+
+        if let Some(reason) = &self.reason {
+            write!(f, " ({})", reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses every ref line of `git push --porcelain` output, in order.
+pub fn parse_porcelain(stdout: &str) -> Vec<RefUpdate> {
+    stdout.lines().filter_map(RefUpdate::parse).collect()
+}
+
+fn abbreviate(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
@@ -0,0 +1,1161 @@
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::PushConfig;
+use crate::divergence::check_divergences;
+use crate::git_helpers::{
+    GitError, GitResult, branch_upstream, current_branch, ensure_remotes_exist, is_git_repo,
+    local_branches, resolve_objects, resolve_ref, run_with_timeout, set_branch_upstream,
+};
+use crate::parallel::run_parallel;
+use crate::porcelain::{RefUpdate, RefUpdateKind, parse_porcelain};
+use crate::remotes::{Remote, RemoteRole, RemoteSet, glob_match};
+use crate::report::remote_width;
+use crate::retry::{FailureKind, RetryPolicy, classify_failure};
+
+use serde::Deserialize;
+
+const ABANDONED: &str = "not attempted after an earlier failure";
+const TAG_PREFIX: &str = "refs/tags/";
+
+/// Refs replicated to remotes in mirror mode.
+const MIRROR_REFSPECS: [&str; 3] = [
+    "+refs/heads/*:refs/heads/*",
+    "+refs/tags/*:refs/tags/*",
+    "+refs/notes/*:refs/notes/*",
+];
+
+/// Settings for a single `push_to_remotes` run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PushOptions {
+    /// Maximum number of remotes pushed at the same time. `None` pushes to
+    /// every remote at once.
+    pub jobs: Option<usize>,
+    /// Attempt every remote even after one has failed.
+    pub keep_going: bool,
+    pub policy: SuccessPolicy,
+    pub retry: RetryPolicy,
+    /// Replaces the timeout of every remote when set.
+    pub timeout: Option<Duration>,
+    pub branches: BranchSelection,
+    pub tags: TagMode,
+    /// Allow non-fast-forward branch updates, but only where each remote's
+    /// branch is still where that remote's own remote-tracking ref says.
+    pub force_with_lease: bool,
+    /// Update either every selected ref on a remote or none of them, so a
+    /// tag never lands without its branch. Remotes that do not advertise
+    /// atomic pushes fail with [`FailureKind::AtomicUnsupported`].
+    pub atomic: bool,
+    /// The remote pushed branches start tracking when they have no
+    /// upstream yet.
+    pub upstream: UpstreamTarget,
+    /// After a successful push, read each pushed ref back with
+    /// `git ls-remote` and fail the remote if it points elsewhere, e.g.
+    /// because a server hook rewrote it.
+    pub verify: bool,
+    /// Fetch every remote first and refuse to push a branch whose history
+    /// differs between two remotes with [`GitError::Diverged`].
+    pub check_divergence: bool,
+    /// Run `git push --dry-run` to preview the ref updates without sending
+    /// anything.
+    pub dry_run: bool,
+    /// Results for remotes whose check before the push already failed, such
+    /// as mirrors in [`MirrorPreview::failures`]. They are reported as they
+    /// are, and those remotes are not pushed to.
+    pub prechecked: Vec<RemotePushResult>,
+}
+
+impl PushOptions {
+    /// Takes the defaults from the `[push]` table of a configuration file.
+    /// Its timeout is already part of each [`Remote`], so `timeout` is
+    /// left unset.
+    pub fn from_config(config: &PushConfig) -> Self {
+        PushOptions {
+            jobs: config.jobs,
+            keep_going: config.keep_going.unwrap_or(false),
+            policy: config.policy.unwrap_or_default(),
+            retry: RetryPolicy::from_config(&config.retry),
+            timeout: None,
+            branches: BranchSelection::Current,
+            tags: TagMode::None,
+            force_with_lease: false,
+            atomic: config.atomic.unwrap_or(false),
+            upstream: config.upstream.clone().unwrap_or_default(),
+            verify: config.verify.unwrap_or(true),
+            check_divergence: config.check_divergence.unwrap_or(true),
+            dry_run: false,
+            prechecked: Vec::new(),
+        }
+    }
+}
+
+/// Which local branches a push sends.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BranchSelection {
+    /// The branch HEAD points at.
+    #[default]
+    Current,
+    /// Every local branch.
+    All,
+    /// Local branches matching any of these patterns (`*` and `?`
+    /// wildcards).
+    Matching(Vec<String>),
+    /// The commit HEAD points at, pushed to the branch of this name on
+    /// every remote. Works with a detached HEAD.
+    HeadTo(String),
+}
+
+/// Which tags a push sends along with, or instead of, the current branch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TagMode {
+    /// Only the branch.
+    #[default]
+    None,
+    /// The branch and the annotated tags reachable from it (`--follow-tags`).
+    Follow,
+    /// The branch and every local tag (`--tags`).
+    All,
+    /// Just this one tag, without any branch.
+    Single(String),
+}
+
+/// Decides whether a push as a whole succeeded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum SuccessPolicy {
+    /// Every remote must succeed.
+    #[default]
+    All,
+    /// Every primary remote must succeed; secondaries are best-effort.
+    Primary,
+    /// At least this many remotes must succeed.
+    Quorum(usize),
+}
+
+impl SuccessPolicy {
+    /// Whether a failure on a remote with `role` already decides the push,
+    /// so that no further remotes need to be started.
+    fn is_fatal(self, role: RemoteRole) -> bool {
+        match self {
+            SuccessPolicy::All => true,
+            SuccessPolicy::Primary => role == RemoteRole::Primary,
+            SuccessPolicy::Quorum(_) => false,
+        }
+    }
+}
+
+impl FromStr for SuccessPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "all" => Ok(SuccessPolicy::All),
+            "primary" => Ok(SuccessPolicy::Primary),
+            _ => match value.strip_prefix("quorum=").map(str::parse::<usize>) {
+                Some(Ok(count)) if count >= 1 => Ok(SuccessPolicy::Quorum(count)),
+                _ => Err(format!(
+                    "expected 'all', 'primary' or 'quorum=N' with N >= 1, got '{}'",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for SuccessPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for SuccessPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuccessPolicy::All => write!(f, "all"),
+            SuccessPolicy::Primary => write!(f, "primary"),
+            SuccessPolicy::Quorum(count) => write!(f, "quorum={}", count),
+        }
+    }
+}
+
+/// Which remote, if any, a push makes the upstream of pushed branches that
+/// do not track anything yet.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum UpstreamTarget {
+    /// [`RemoteSet::primary`].
+    #[default]
+    Primary,
+    /// The remote of this name.
+    Remote(String),
+    /// Leave tracking configuration alone.
+    Off,
+}
+
+impl FromStr for UpstreamTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "" => Err("expected 'primary', 'none' or a remote name".to_string()),
+            "primary" => Ok(UpstreamTarget::Primary),
+            "none" => Ok(UpstreamTarget::Off),
+            name => Ok(UpstreamTarget::Remote(name.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for UpstreamTarget {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for UpstreamTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpstreamTarget::Primary => write!(f, "primary"),
+            UpstreamTarget::Remote(name) => write!(f, "{}", name),
+            UpstreamTarget::Off => write!(f, "none"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushStatus {
+    Success,
+    /// The remote already had everything; nothing was sent.
+    UpToDate,
+    /// The remote was not pushed to, with the reason why.
+    Skipped(String),
+    Failed,
+}
+
+impl PushStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            PushStatus::Success => "success",
+            PushStatus::UpToDate => "up-to-date",
+            PushStatus::Skipped(_) => "skipped",
+            PushStatus::Failed => "failed",
+        }
+    }
+}
+
+/// What happened on one remote, together with the output git produced for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePushResult {
+    pub remote: String,
+    pub role: RemoteRole,
+    pub status: PushStatus,
+    /// Why the last attempt failed, for failed pushes.
+    pub failure: Option<FailureKind>,
+    /// Number of times git was run; 0 when the remote was skipped.
+    pub attempts: u32,
+    /// How long the last attempt ran.
+    pub elapsed: Duration,
+    /// What happened to each ref, in the order git reported them.
+    pub refs: Vec<RefUpdate>,
+    /// Arguments of the last git invocation; empty when skipped.
+    pub command: Vec<String>,
+    /// Pushed refs that the remote reported at another object afterwards.
+    pub mismatches: Vec<RefMismatch>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// A ref that `git ls-remote` showed somewhere other than where it was
+/// pushed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefMismatch {
+    pub reference: String,
+    pub expected: String,
+    /// `None` when the ref does not exist on the remote.
+    pub actual: Option<String>,
+}
+
+impl RemotePushResult {
+    fn skipped(remote: &Remote, reason: String) -> Self {
+        RemotePushResult {
+            remote: remote.name.clone(),
+            role: remote.role,
+            status: PushStatus::Skipped(reason),
+            failure: None,
+            attempts: 0,
+            elapsed: Duration::ZERO,
+            refs: Vec::new(),
+            command: Vec::new(),
+            mismatches: Vec::new(),
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        self.status == PushStatus::Failed
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, PushStatus::Success | PushStatus::UpToDate)
+    }
+
+    /// The failure of a failed push as an error; timeouts become
+    /// [`GitError::Timeout`] and wrong refs found after the push
+    /// [`GitError::VerificationFailed`] for the first of them.
+    pub fn error(&self) -> Option<GitError> {
+        match self.failure? {
+            FailureKind::Timeout => Some(GitError::Timeout {
+                remote: self.remote.clone(),
+                after: self.elapsed,
+            }),
+            FailureKind::Verification if !self.mismatches.is_empty() => {
+                Some(self.mismatches[0].to_error(&self.remote))
+            }
+            _ => Some(GitError::CommandFailed(self.stderr.clone())),
+        }
+    }
+
+    /// Refs that git or the remote refused to update.
+    pub fn rejected_refs(&self) -> Vec<&RefUpdate> {
+        self.refs
+            .iter()
+            .filter(|update| update.kind.is_rejected())
+            .collect()
+    }
+
+    /// Names of the tags git or the remote refused to update.
+    pub fn rejected_tags(&self) -> Vec<&str> {
+        self.rejected_refs()
+            .into_iter()
+            .filter(|update| update.is_tag())
+            .map(RefUpdate::short_name)
+            .collect()
+    }
+
+    /// Whether the remote was skipped because an earlier failure stopped
+    /// the run, as opposed to not being configured for the branch.
+    pub fn was_abandoned(&self) -> bool {
+        matches!(&self.status, PushStatus::Skipped(reason) if reason == ABANDONED)
+    }
+}
+
+impl RefMismatch {
+    pub fn to_error(&self, remote: &str) -> GitError {
+        GitError::VerificationFailed {
+            remote: remote.to_string(),
+            reference: self.reference.clone(),
+            expected: self.expected.clone(),
+            actual: self.actual.clone(),
+        }
+    }
+}
+
+/// Overall result of a push across every remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushSummary {
+    /// No remote failed.
+    AllSucceeded,
+    /// Some remotes failed, but not enough to violate the success policy.
+    PolicySatisfied,
+    /// The success policy was violated, though some remotes succeeded.
+    PartialFailure,
+    /// Every remote that was attempted failed.
+    AllFailed,
+    /// Every remote was skipped, for example because none of their
+    /// `branches` filters accepts the branch, so nothing was pushed.
+    NothingPushed,
+}
+
+impl PushSummary {
+    /// Process exit code for this summary: 0 when the policy is met, 3 for a
+    /// partial failure, 1 when everything failed and 2, like other usage
+    /// errors, when no remote takes what was asked for.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            PushSummary::AllSucceeded | PushSummary::PolicySatisfied => 0,
+            PushSummary::PartialFailure => 3,
+            PushSummary::AllFailed => 1,
+            PushSummary::NothingPushed => 2,
+        }
+    }
+}
+
+/// Per-remote results of a push, in the order of the remote set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushReport {
+    /// What was pushed, e.g. `main` or `tag v1.0`.
+    pub target: String,
+    /// Whether this previews a push rather than reporting one.
+    pub dry_run: bool,
+    pub policy: SuccessPolicy,
+    pub results: Vec<RemotePushResult>,
+}
+
+impl PushReport {
+    pub fn get(&self, remote: &str) -> Option<&RemotePushResult> {
+        self.results.iter().find(|result| result.remote == remote)
+    }
+
+    pub fn failed_remotes(&self) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|result| result.is_failure())
+            .map(|result| result.remote.as_str())
+            .collect()
+    }
+
+    /// Whether the results meet the report's success policy.
+    pub fn policy_satisfied(&self) -> bool {
+        match self.policy {
+            SuccessPolicy::All => !self.results.iter().any(|result| result.is_failure()),
+            SuccessPolicy::Primary => {
+                let primaries: Vec<&RemotePushResult> = self
+                    .results
+                    .iter()
+                    .filter(|result| result.role == RemoteRole::Primary)
+                    .collect();
+                let primaries = if primaries.is_empty() {
+                    self.results.first().into_iter().collect()
+                } else {
+                    primaries
+                };
+                primaries
+                    .iter()
+                    .all(|result| !result.is_failure() && !result.was_abandoned())
+            }
+            SuccessPolicy::Quorum(count) => {
+                self.results
+                    .iter()
+                    .filter(|result| result.is_success())
+                    .count()
+                    >= count
+            }
+        }
+    }
+
+    pub fn summary(&self) -> PushSummary {
+        if !self.results.is_empty()
+            && self
+                .results
+                .iter()
+                .all(|result| matches!(result.status, PushStatus::Skipped(_)))
+        {
+            return PushSummary::NothingPushed;
+        }
+
+        let failed = self.results.iter().any(|result| result.is_failure());
+        let succeeded = self.results.iter().any(|result| result.is_success());
+
+        match (failed, self.policy_satisfied(), succeeded) {
+            (false, true, _) => PushSummary::AllSucceeded,
+            (true, true, _) => PushSummary::PolicySatisfied,
+            (_, false, true) => PushSummary::PartialFailure,
+            (_, false, false) => PushSummary::AllFailed,
+        }
+    }
+
+    /// Turns a report that violates its success policy into
+    /// [`GitError::PushFailed`], and one where every remote was skipped into
+    /// [`GitError::Usage`].
+    pub fn check(&self) -> GitResult<()> {
+        match self.summary() {
+            PushSummary::AllSucceeded | PushSummary::PolicySatisfied => Ok(()),
+            PushSummary::NothingPushed => Err(GitError::Usage(format!(
+                "Every remote skipped {}, so nothing was pushed",
+                self.target
+            ))),
+            summary => Err(GitError::PushFailed {
+                remotes: self
+                    .failed_remotes()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                partial: summary == PushSummary::PartialFailure,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for PushReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = remote_width(self.results.iter().map(|result| result.remote.as_str()));
+
+        let title = if self.dry_run {
+            "Dry run of push"
+        } else {
+            "Push report"
+        };
+        writeln!(
+            f,
+            "{} for {} (policy: {}):",
+            title, self.target, self.policy
+        )?;
+
+        for result in &self.results {
+            let role = match result.role {
+                RemoteRole::Primary => "primary",
+                RemoteRole::Secondary => "secondary",
+            };
+            write!(
+                f,
+                "  {:width$}  {:9}  {}",
+                result.remote,
+                role,
+                result.status.label(),
+                width = width
+            )?;
+            if let PushStatus::Skipped(reason) = &result.status {
+                write!(f, " ({})", reason)?;
+            }
+            if let Some(failure) = result.failure {
+                write!(f, " [{}]", failure.label())?;
+            }
+            if result.attempts > 1 {
+                write!(f, " after {} attempts", result.attempts)?;
+            }
+            writeln!(f)?;
+
+            if self.dry_run && !result.command.is_empty() {
+                writeln!(f, "      $ git {}", result.command.join(" "))?;
+            }
+            // Unchanged refs are only listed in previews.
+            for update in result
+                .refs
+                .iter()
+                .filter(|update| self.dry_run || update.kind != RefUpdateKind::UpToDate)
+            {
+                writeln!(f, "      {}", update)?;
+            }
+
+            if result.failure == Some(FailureKind::StaleLease) {
+                writeln!(
+                    f,
+                    "      {} changed since it was last fetched; fetch it and review the new commits before forcing",
+                    result.remote
+                )?;
+            }
+            if result.failure == Some(FailureKind::AtomicUnsupported) {
+                writeln!(
+                    f,
+                    "      {} does not advertise atomic pushes; nothing was updated there",
+                    result.remote
+                )?;
+            }
+            if result.is_failure() {
+                for line in result.stderr.lines().filter(|line| !line.trim().is_empty()) {
+                    writeln!(f, "      {}", line)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Pushes the current branch to every remote in `remotes` concurrently and
+/// prints a report with one line per remote, in the order of the set.
+/// `options.branches` selects further branches, pushed to each remote in a
+/// single git invocation, and `options.tags` adds tags to the push or
+/// pushes a single tag instead. Mirror remotes ignore both and replicate
+/// every branch, tag and note, deleting refs that are gone locally; use
+/// [`pending_mirror_deletions`] to confirm that beforehand. With
+/// `options.force_with_lease` each remote's lease is checked against its
+/// own remote-tracking refs, so a remote that moved is reported as failed
+/// with [`FailureKind::StaleLease`] while the others are still updated.
+/// With `options.check_divergence` nothing is pushed while any selected
+/// branch has diverged between two remotes. Unless `options.verify` is off, each successful push is checked with
+/// `git ls-remote`, see [`RemotePushResult::mismatches`].
+///
+/// Failed pushes are recorded in the returned report rather than returned
+/// as errors; use [`PushReport::check`] to apply the success policy. Unless
+/// `options.keep_going` is set, no new pushes are started once a failure
+/// has decided the outcome under that policy, and the remaining remotes are
+/// reported as skipped.
+pub fn push_to_remotes(remotes: &RemoteSet, options: &PushOptions) -> GitResult<PushReport> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    ensure_remotes_exist(remotes)?;
+
+    if let SuccessPolicy::Quorum(count) = options.policy
+        && count > remotes.len()
+    {
+        return Err(GitError::Usage(format!(
+            "Quorum of {} cannot be reached with {} remote(s)",
+            count,
+            remotes.len()
+        )));
+    }
+
+    let branches = selected_branches(options)?;
+    let upstream = upstream_remote(remotes, options)?;
+    if options.check_divergence && !branches.is_empty() {
+        let divergences = check_divergences(remotes, &branches, options.timeout)?;
+        if !divergences.is_empty() {
+            for divergence in &divergences {
+                print!("{}", divergence);
+            }
+            return Err(GitError::Diverged(divergences));
+        }
+    }
+
+    let branch_list = match &options.branches {
+        BranchSelection::HeadTo(branch) => format!("HEAD to {}", branch),
+        _ => branches.join(", "),
+    };
+    let target = match &options.tags {
+        TagMode::Single(tag) => format!("tag {}", tag),
+        TagMode::Follow => format!("{} and reachable annotated tags", branch_list),
+        TagMode::All => format!("{} and all tags", branch_list),
+        TagMode::None => branch_list,
+    };
+
+    // Start primaries first so that a limited number of jobs goes to the
+    // remotes that decide the outcome; results are put back in set order.
+    let mut targets: Vec<(usize, &Remote)> = remotes.iter().enumerate().collect();
+    targets.sort_by_key(|(_, remote)| remote.role != RemoteRole::Primary);
+
+    let jobs = options.jobs.unwrap_or(targets.len());
+    let stop = AtomicBool::new(false);
+
+    let verb = if options.dry_run {
+        "Dry run: pushing"
+    } else {
+        "Pushing"
+    };
+    println!("{} {} to {}...", verb, target, remotes.names().join(", "));
+
+    let results = run_parallel(&targets, jobs, |(_, remote)| {
+        if !options.keep_going && stop.load(Ordering::SeqCst) {
+            return RemotePushResult::skipped(remote, ABANDONED.to_string());
+        }
+
+        let timeout = options.timeout.or(remote.timeout);
+        let mut result = match options
+            .prechecked
+            .iter()
+            .find(|result| result.remote == remote.name)
+        {
+            Some(result) => result.clone(),
+            None => push_with_retry(remote, &branches, options, timeout),
+        };
+        if options.verify && !options.dry_run && result.is_success() {
+            verify_remote(remote, &mut result, timeout);
+        }
+        if result.is_failure() && options.policy.is_fatal(remote.role) {
+            stop.store(true, Ordering::SeqCst);
+        }
+        result
+    });
+
+    let mut results: Vec<(usize, RemotePushResult)> = targets
+        .iter()
+        .map(|(index, _)| *index)
+        .zip(results)
+        .collect();
+    results.sort_by_key(|(index, _)| *index);
+
+    let report = PushReport {
+        target,
+        dry_run: options.dry_run,
+        policy: options.policy,
+        results: results.into_iter().map(|(_, result)| result).collect(),
+    };
+
+    print!("{}", report);
+
+    match report.summary() {
+        _ if report.dry_run => println!("Dry run complete; nothing was pushed."),
+        PushSummary::AllSucceeded => println!("All changes pushed successfully to all remotes!"),
+        PushSummary::NothingPushed => println!("Every remote was skipped; nothing was pushed."),
+        PushSummary::PolicySatisfied => println!(
+            "Push succeeded under the '{}' policy, but some remotes failed.",
+            report.policy
+        ),
+        _ => {}
+    }
+
+    if !report.dry_run {
+        set_upstreams(upstream, &branches, options, &report);
+    }
+
+    Ok(report)
+}
+
+/// The remote `options.upstream` chooses, checked before anything is
+/// pushed. Naming a remote outside `remotes` is a usage error; mirror
+/// remotes never become an upstream, with a warning when one was named.
+fn upstream_remote<'a>(
+    remotes: &'a RemoteSet,
+    options: &PushOptions,
+) -> GitResult<Option<&'a Remote>> {
+    let remote = match &options.upstream {
+        UpstreamTarget::Off => return Ok(None),
+        UpstreamTarget::Primary => remotes.primary(),
+        UpstreamTarget::Remote(name) => Some(remotes.get(name).ok_or_else(|| {
+            GitError::Usage(format!(
+                "Cannot set upstreams to '{}': it is not one of the remotes pushed to ({})",
+                name,
+                remotes.names().join(", ")
+            ))
+        })?),
+    };
+
+    match remote {
+        Some(remote) if remote.is_mirror() => {
+            if matches!(options.upstream, UpstreamTarget::Remote(_)) {
+                println!(
+                    "Warning: not setting upstreams to {}: mirror remotes never become an upstream.",
+                    remote.name
+                );
+            }
+            Ok(None)
+        }
+        remote => Ok(remote),
+    }
+}
+
+/// Points pushed branches without an upstream at `remote`, as chosen by
+/// [`upstream_remote`], once the push to it has succeeded. Existing
+/// tracking configuration is left alone.
+fn set_upstreams(
+    remote: Option<&Remote>,
+    branches: &[String],
+    options: &PushOptions,
+    report: &PushReport,
+) {
+    if matches!(options.branches, BranchSelection::HeadTo(_)) {
+        return;
+    }
+
+    let Some(remote) = remote else {
+        return;
+    };
+    if !report
+        .get(&remote.name)
+        .is_some_and(RemotePushResult::is_success)
+    {
+        return;
+    }
+
+    for branch in branches
+        .iter()
+        .filter(|branch| remote.accepts_branch(branch))
+        .filter(|branch| branch_upstream(branch).is_none())
+    {
+        match set_branch_upstream(branch, &remote.name) {
+            Ok(()) => println!(
+                "Branch '{}' set up to track '{}/{}'.",
+                branch, remote.name, branch
+            ),
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+    }
+}
+
+/// Pushes, retrying with backoff for as long as the failure is transient
+/// and the policy allows.
+fn push_with_retry(
+    remote: &Remote,
+    branches: &[String],
+    options: &PushOptions,
+    timeout: Option<Duration>,
+) -> RemotePushResult {
+    let retry = &options.retry;
+    let mut attempt = 1;
+
+    loop {
+        let mut result = push_refs(remote, branches, options, timeout);
+        result.attempts = attempt;
+
+        let transient = result.failure.is_some_and(FailureKind::is_transient);
+        if !transient || attempt > retry.retries {
+            return result;
+        }
+
+        let delay = retry.delay(attempt);
+        let reason = match result.failure {
+            Some(FailureKind::Timeout) => "timed out",
+            _ => "failed with a network error",
+        };
+        println!(
+            "Push to {} {}; retrying in {:.1}s ({}/{})...",
+            remote.name,
+            reason,
+            delay.as_secs_f64(),
+            attempt,
+            retry.retries
+        );
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Pushes those of `branches` the remote accepts, plus tags as selected by
+/// `options.tags`, to one remote in a single git invocation. Without
+/// branches only the single tag of [`TagMode::Single`] is pushed.
+fn push_refs(
+    remote: &Remote,
+    branches: &[String],
+    options: &PushOptions,
+    timeout: Option<Duration>,
+) -> RemotePushResult {
+    let name = remote.name.as_str();
+    let tags = &options.tags;
+
+    let mut args = vec!["push".to_string(), "--porcelain".to_string()];
+    if options.dry_run {
+        args.push("--dry-run".to_string());
+    }
+    if options.atomic {
+        args.push("--atomic".to_string());
+    }
+    for option in &remote.push_options {
+        args.push(format!("--push-option={}", option));
+    }
+
+    if remote.is_mirror() {
+        args.extend(mirror_args(name));
+    } else {
+        let accepted: Vec<&String> = branches
+            .iter()
+            .filter(|branch| remote.accepts_branch(branch))
+            .collect();
+        if accepted.is_empty() && !branches.is_empty() {
+            let reason = match branches {
+                [branch] => format!("branch '{}' is not configured for this remote", branch),
+                _ => "none of the branches is configured for this remote".to_string(),
+            };
+            return RemotePushResult::skipped(remote, reason);
+        }
+
+        match tags {
+            TagMode::Follow => args.push("--follow-tags".to_string()),
+            TagMode::All => args.push("--tags".to_string()),
+            TagMode::None | TagMode::Single(_) => {}
+        }
+        if options.force_with_lease {
+            args.extend(lease_args(name, &accepted));
+        }
+        args.push(name.to_string());
+        args.extend(accepted.iter().map(|branch| match &options.branches {
+            BranchSelection::HeadTo(_) => format!("HEAD:refs/heads/{}", branch),
+            _ => format!("refs/heads/{0}:refs/heads/{0}", branch),
+        }));
+        if let TagMode::Single(tag) = tags {
+            args.push(format!("{}{}", TAG_PREFIX, tag));
+        }
+    }
+
+    match run_with_timeout(Command::new("git").args(&args), timeout) {
+        Ok(run) => {
+            let output = run.output;
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            let mut stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let refs = resolve_ref_updates(&stdout);
+            let up_to_date = !refs.is_empty()
+                && refs
+                    .iter()
+                    .all(|update| update.kind == RefUpdateKind::UpToDate);
+            let status = if run.timed_out || !output.status.success() {
+                PushStatus::Failed
+            } else if up_to_date || stderr.contains("Everything up-to-date") {
+                PushStatus::UpToDate
+            } else {
+                PushStatus::Success
+            };
+            let failure = if run.timed_out {
+                let error = GitError::Timeout {
+                    remote: name.to_string(),
+                    after: run.elapsed,
+                };
+                stderr.push_str(&format!("{}\n", error));
+                Some(FailureKind::Timeout)
+            } else {
+                (status == PushStatus::Failed)
+                    .then(|| classify_failure(&format!("{}\n{}", stdout, stderr)))
+            };
+
+            RemotePushResult {
+                remote: name.to_string(),
+                role: remote.role,
+                status,
+                failure,
+                attempts: 1,
+                elapsed: run.elapsed,
+                refs,
+                command: args,
+                mismatches: Vec::new(),
+                stdout,
+                stderr,
+            }
+        }
+        Err(e) => RemotePushResult {
+            remote: name.to_string(),
+            role: remote.role,
+            status: PushStatus::Failed,
+            failure: Some(FailureKind::Other),
+            attempts: 1,
+            elapsed: Duration::ZERO,
+            refs: Vec::new(),
+            command: args,
+            mismatches: Vec::new(),
+            stdout: String::new(),
+            stderr: format!("Error running git push: {}", e),
+        },
+    }
+}
+
+/// Reads the refs of a successful push back from the remote with
+/// `git ls-remote` and marks the result as failed with
+/// [`FailureKind::Verification`] when any of them is missing or points at
+/// a different object than was pushed.
+fn verify_remote(remote: &Remote, result: &mut RemotePushResult, timeout: Option<Duration>) {
+    let expected: Vec<(&str, &str)> = result
+        .refs
+        .iter()
+        .filter(|update| update.kind != RefUpdateKind::Deleted && !update.kind.is_rejected())
+        .filter_map(|update| Some((update.destination.as_str(), update.new_sha.as_deref()?)))
+        .collect();
+    if expected.is_empty() {
+        return;
+    }
+
+    let mut args = vec!["ls-remote".to_string(), remote.name.clone()];
+    // A mirror pushes every ref, so ask for all of them at once.
+    if !remote.is_mirror() {
+        args.extend(expected.iter().map(|(reference, _)| reference.to_string()));
+    }
+
+    let run = match run_with_timeout(Command::new("git").args(&args), timeout) {
+        Ok(run) if !run.timed_out && run.output.status.success() => run,
+        Ok(run) => {
+            let reason = if run.timed_out {
+                "timed out".to_string()
+            } else {
+                String::from_utf8_lossy(&run.output.stderr)
+                    .trim()
+                    .to_string()
+            };
+            fail_verification(
+                result,
+                format!("Could not verify the pushed refs: {}", reason),
+            );
+            return;
+        }
+        Err(e) => {
+            fail_verification(result, format!("Could not verify the pushed refs: {}", e));
+            return;
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&run.output.stdout);
+    let advertised: Vec<(&str, &str)> = stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(sha, reference)| (reference.trim(), sha.trim()))
+        .collect();
+
+    let mismatches: Vec<RefMismatch> = expected
+        .iter()
+        .filter_map(|(reference, sha)| {
+            let actual = advertised
+                .iter()
+                .find(|(name, _)| name == reference)
+                .map(|(_, actual)| actual.to_string());
+            (actual.as_deref() != Some(*sha)).then(|| RefMismatch {
+                reference: reference.to_string(),
+                expected: sha.to_string(),
+                actual,
+            })
+        })
+        .collect();
+
+    if !mismatches.is_empty() {
+        let errors: Vec<String> = mismatches
+            .iter()
+            .map(|mismatch| mismatch.to_error(&remote.name).to_string())
+            .collect();
+        fail_verification(result, errors.join("\n"));
+        result.mismatches = mismatches;
+    }
+}
+
+fn fail_verification(result: &mut RemotePushResult, message: String) {
+    result.status = PushStatus::Failed;
+    result.failure = Some(FailureKind::Verification);
+    result.stderr.push_str(&format!("{}\n", message));
+}
+
+/// One `--force-with-lease` per branch, expecting the remote's branch at
+/// the value of this remote's own remote-tracking ref, or absent when there
+/// is none, so that a stale view of one remote never vouches for another.
+fn lease_args(remote: &str, branches: &[&String]) -> Vec<String> {
+    let tracking: Vec<String> = branches
+        .iter()
+        .map(|branch| format!("refs/remotes/{}/{}", remote, branch))
+        .collect();
+    let revs: Vec<&str> = tracking.iter().map(String::as_str).collect();
+
+    branches
+        .iter()
+        .zip(resolve_objects(&revs))
+        .map(|(branch, expected)| {
+            format!(
+                "--force-with-lease=refs/heads/{}:{}",
+                branch,
+                expected.unwrap_or_default()
+            )
+        })
+        .collect()
+}
+
+/// Arguments that make `git push` replicate every branch, tag and note to
+/// `remote`, forcing updates and deleting refs that no longer exist locally.
+fn mirror_args(remote: &str) -> Vec<String> {
+    let mut args = vec!["--prune".to_string(), remote.to_string()];
+    args.extend(MIRROR_REFSPECS.iter().map(|refspec| refspec.to_string()));
+    args
+}
+
+/// What a dry run found a push would do to the mirror remotes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorPreview {
+    /// Refs each mirror would lose. Mirrors without deletions are left out.
+    pub deletions: Vec<(String, Vec<String>)>,
+    /// Mirrors the dry run failed on, as failed results to pass on in
+    /// [`PushOptions::prechecked`], so that the other remotes can still be
+    /// pushed to.
+    pub failures: Vec<RemotePushResult>,
+}
+
+/// Finds the refs a push would delete on each mirror remote in `remotes`
+/// with a dry run, retried like a push. The branches are selected first,
+/// so that e.g. a detached HEAD is reported before any remote is contacted.
+pub fn pending_mirror_deletions(
+    remotes: &RemoteSet,
+    options: &PushOptions,
+) -> GitResult<MirrorPreview> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    ensure_remotes_exist(remotes)?;
+    selected_branches(options)?;
+
+    let mirrors: Vec<&Remote> = remotes.iter().filter(|remote| remote.is_mirror()).collect();
+    let dry_run = PushOptions {
+        dry_run: true,
+        ..options.clone()
+    };
+    let results = run_parallel(&mirrors, mirrors.len(), |remote| {
+        push_with_retry(remote, &[], &dry_run, options.timeout.or(remote.timeout))
+    });
+
+    let mut preview = MirrorPreview::default();
+    for mut result in results {
+        if result.is_failure() {
+            result.stderr = format!(
+                "Could not list the refs a push would delete:\n{}",
+                result.stderr
+            );
+            preview.failures.push(result);
+            continue;
+        }
+
+        let refs: Vec<String> = result
+            .refs
+            .into_iter()
+            .filter(|update| update.kind == RefUpdateKind::Deleted)
+            .map(|update| update.destination)
+            .collect();
+        if !refs.is_empty() {
+            preview.deletions.push((result.remote, refs));
+        }
+    }
+
+    Ok(preview)
+}
+
+/// The local branches `options` selects; none when a single tag is pushed.
+fn selected_branches(options: &PushOptions) -> GitResult<Vec<String>> {
+    match &options.tags {
+        TagMode::Single(tag) => {
+            if resolve_ref(&format!("{}{}", TAG_PREFIX, tag)).is_none() {
+                return Err(GitError::Usage(format!("Tag '{}' does not exist", tag)));
+            }
+            Ok(Vec::new())
+        }
+        _ => select_branches(&options.branches),
+    }
+}
+
+/// Resolves `selection` to local branch names, failing when nothing matches.
+fn select_branches(selection: &BranchSelection) -> GitResult<Vec<String>> {
+    let branches = match selection {
+        BranchSelection::Current => return Ok(vec![current_branch()?]),
+        BranchSelection::HeadTo(branch) => {
+            if resolve_ref("HEAD").is_none() {
+                return Err(GitError::CommandFailed(
+                    "HEAD does not point at a commit yet".to_string(),
+                ));
+            }
+            return Ok(vec![branch.clone()]);
+        }
+        BranchSelection::All => local_branches()?,
+        BranchSelection::Matching(patterns) => local_branches()?
+            .into_iter()
+            .filter(|branch| patterns.iter().any(|pattern| glob_match(pattern, branch)))
+            .collect(),
+    };
+
+    if branches.is_empty() {
+        return Err(GitError::Usage(match selection {
+            BranchSelection::Matching(patterns) => {
+                format!("No local branches match '{}'", patterns.join("', '"))
+            }
+            _ => "There are no local branches to push".to_string(),
+        }));
+    }
+
+    Ok(branches)
+}
+
+/// Parses the porcelain output of a push and fills in full object names:
+/// the new value from the local source, and the old one where it is known
+/// locally.
+fn resolve_ref_updates(stdout: &str) -> Vec<RefUpdate> {
+    let mut updates = parse_porcelain(stdout);
+
+    let revs: Vec<&str> = updates
+        .iter()
+        .flat_map(|update| {
+            [
+                update.source.as_str(),
+                update.old_sha.as_deref().unwrap_or(""),
+            ]
+        })
+        .collect();
+    let mut names = resolve_objects(&revs).into_iter();
+
+    for update in &mut updates {
+        let (new, old) = (names.next().flatten(), names.next().flatten());
+        if !update.source.is_empty() && new.is_some() {
+            update.new_sha = new;
+        }
+        if old.is_some() {
+            update.old_sha = old;
+        }
+    }
+
+    updates
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+
+use crate::divergence::{Divergence, find_divergences};
+use crate::git_helpers::{
+    GitError, GitResult, current_branch, ensure_remotes_exist, is_git_repo, resolve_ref,
+    run_with_timeout,
+};
+use crate::porcelain::parse_porcelain;
+use crate::push::{PushOptions, pending_mirror_deletions, push_to_remotes};
+use crate::remotes::RemoteSet;
+
+/// How `andiamo reconcile` resolves a branch that diverged between the
+/// primary remote and another remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconcileStrategy {
+    /// Force the primary's branch onto the other remote, with a lease on
+    /// the value just fetched, dropping the commits only that remote has.
+    Overwrite,
+    /// Merge both sides into the local branch and push it everywhere.
+    Merge,
+    /// Keep the other remote's commits on a local `review/<remote>/<branch>`
+    /// branch for a person to look at.
+    Review,
+}
+
+impl FromStr for ReconcileStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "overwrite" => Ok(ReconcileStrategy::Overwrite),
+            "merge" => Ok(ReconcileStrategy::Merge),
+            "review" => Ok(ReconcileStrategy::Review),
+            _ => Err(format!(
+                "expected 'overwrite', 'merge' or 'review', got '{}'",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReconcileStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconcileStrategy::Overwrite => write!(f, "overwrite"),
+            ReconcileStrategy::Merge => write!(f, "merge"),
+            ReconcileStrategy::Review => write!(f, "review"),
+        }
+    }
+}
+
+/// The divergences of one branch between the primary remote and the other
+/// remotes. In each [`Divergence`], `other` is the primary and `remote` the
+/// remote that split from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconcilePlan {
+    pub branch: String,
+    pub primary: String,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Fetches every remote and finds where `branch`, or the current branch,
+/// has diverged between the [primary](RemoteSet::primary) remote and each
+/// other remote.
+pub fn plan_reconcile(remotes: &RemoteSet, branch: Option<&str>) -> GitResult<ReconcilePlan> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    ensure_remotes_exist(remotes)?;
+
+    let primary = remotes
+        .primary()
+        .map(|remote| remote.name.clone())
+        .ok_or_else(|| GitError::Usage("No remotes to reconcile".to_string()))?;
+    if remotes.len() < 2 {
+        return Err(GitError::Usage(format!(
+            "Reconciling needs another remote besides '{}'",
+            primary
+        )));
+    }
+
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None => current_branch()?,
+    };
+
+    let divergences = find_divergences(remotes, std::slice::from_ref(&branch))?
+        .into_iter()
+        .filter_map(|divergence| {
+            if divergence.other == primary {
+                Some(divergence)
+            } else if divergence.remote == primary {
+                Some(Divergence {
+                    remote: divergence.other,
+                    other: divergence.remote,
+                    only_remote: divergence.only_other,
+                    only_other: divergence.only_remote,
+                    ..divergence
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Ok(ReconcilePlan {
+        branch,
+        primary,
+        divergences,
+    })
+}
+
+/// Resolves every divergence in `plan` with `strategy`. With
+/// `options.dry_run` the git commands are printed instead of run; the
+/// merge strategy pushes with the rest of `options`. That push does not ask
+/// before deleting refs from mirror remotes, so callers confirm them with
+/// [`pending_mirror_deletions`] first.
+pub fn reconcile(
+    remotes: &RemoteSet,
+    plan: &ReconcilePlan,
+    strategy: ReconcileStrategy,
+    options: &PushOptions,
+) -> GitResult<()> {
+    if plan.divergences.is_empty() {
+        println!(
+            "Branch {} has not diverged between {} and the other remotes; nothing to reconcile.",
+            plan.branch, plan.primary
+        );
+        return Ok(());
+    }
+
+    match strategy {
+        ReconcileStrategy::Overwrite => overwrite(remotes, plan, options.dry_run),
+        ReconcileStrategy::Merge => merge(remotes, plan, options),
+        ReconcileStrategy::Review => review(plan, options.dry_run),
+    }
+}
+
+fn overwrite(remotes: &RemoteSet, plan: &ReconcilePlan, dry_run: bool) -> GitResult<()> {
+    let branch = &plan.branch;
+    let source = format!("refs/remotes/{}/{}", plan.primary, branch);
+
+    for divergence in &plan.divergences {
+        let remote = &divergence.remote;
+        let expected =
+            resolve_ref(&format!("refs/remotes/{}/{}", remote, branch)).unwrap_or_default();
+        let args = vec![
+            "push".to_string(),
+            "--porcelain".to_string(),
+            format!("--force-with-lease=refs/heads/{}:{}", branch, expected),
+            remote.clone(),
+            format!("{}:refs/heads/{}", source, branch),
+        ];
+
+        if dry_run {
+            println!("Would run: git {}", args.join(" "));
+            println!(
+                "  dropping {} commit(s) only {} has",
+                divergence.only_remote.len(),
+                remote
+            );
+            continue;
+        }
+
+        let timeout = remotes.get(remote).and_then(|remote| remote.timeout);
+        let run = run_with_timeout(Command::new("git").args(&args), timeout)?;
+        let stdout = String::from_utf8_lossy(&run.output.stdout);
+        if run.timed_out || !run.output.status.success() {
+            let reason = match parse_porcelain(&stdout)
+                .into_iter()
+                .find(|update| update.kind.is_rejected())
+            {
+                Some(update) => update.to_string(),
+                None if run.timed_out => "timed out".to_string(),
+                None => String::from_utf8_lossy(&run.output.stderr)
+                    .trim()
+                    .to_string(),
+            };
+            return Err(GitError::CommandFailed(format!(
+                "Could not overwrite {} on {}: {}",
+                branch, remote, reason
+            )));
+        }
+
+        println!(
+            "Overwrote {} on {} with {}'s history, dropping {} commit(s).",
+            branch,
+            remote,
+            plan.primary,
+            divergence.only_remote.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn merge(remotes: &RemoteSet, plan: &ReconcilePlan, options: &PushOptions) -> GitResult<()> {
+    let branch = &plan.branch;
+    if current_branch()? != *branch {
+        return Err(GitError::Usage(format!(
+            "Check out {} to merge the remotes' commits into it",
+            branch
+        )));
+    }
+
+    let sides = std::iter::once(&plan.primary)
+        .chain(plan.divergences.iter().map(|divergence| &divergence.remote));
+    for remote in sides {
+        let tracking = format!("refs/remotes/{}/{}", remote, branch);
+        let args = ["merge", "--no-edit", tracking.as_str()];
+
+        if options.dry_run {
+            println!("Would run: git {}", args.join(" "));
+            continue;
+        }
+
+        let output = Command::new("git").args(args).output()?;
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::CommandFailed(format!(
+                "Merging {}/{} stopped: {}{}\nResolve it, commit, and run `andiamo push`.",
+                remote,
+                branch,
+                stdout,
+                stderr.trim_end()
+            )));
+        }
+        println!("Merged {}/{} into {}.", remote, branch, branch);
+    }
+
+    if options.dry_run {
+        println!(
+            "Would then push {} to {}.",
+            branch,
+            remotes.names().join(", ")
+        );
+        let preview = pending_mirror_deletions(remotes, options)?;
+        if !preview.deletions.is_empty() {
+            println!("The push would delete:");
+            for (remote, refs) in &preview.deletions {
+                for name in refs {
+                    println!("  {}: {}", remote, name);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // The remotes still differ from each other until this push lands, and
+    // the local branch now holds both sides.
+    let options = PushOptions {
+        check_divergence: false,
+        ..options.clone()
+    };
+    push_to_remotes(remotes, &options)?.check()
+}
+
+fn review(plan: &ReconcilePlan, dry_run: bool) -> GitResult<()> {
+    for divergence in &plan.divergences {
+        let remote = &divergence.remote;
+        let review = format!("review/{}/{}", remote, plan.branch);
+        let tracking = format!("refs/remotes/{}/{}", remote, plan.branch);
+
+        if resolve_ref(&format!("refs/heads/{}", review)).is_some() {
+            return Err(GitError::Usage(format!(
+                "Branch '{}' already exists; delete it or review it first",
+                review
+            )));
+        }
+
+        let args = ["branch", review.as_str(), tracking.as_str()];
+        if dry_run {
+            println!("Would run: git {}", args.join(" "));
+            continue;
+        }
+
+        let output = Command::new("git").args(args).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::CommandFailed(format!(
+                "Error creating branch '{}': {}",
+                review, stderr
+            )));
+        }
+
+        println!(
+            "Created {} with the {} commit(s) only {} has. Compare with `git log {}/{}..{}`.",
+            review,
+            divergence.only_remote.len(),
+            remote,
+            plan.primary,
+            plan.branch,
+            review
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::url_env_var;
+use crate::fetch::fetch_updates;
+use crate::git_helpers::{GitError, GitResult, get_remote_url, is_bare_repo, run_with_timeout};
+use crate::parallel::run_parallel;
+use crate::porcelain::{RefUpdate, RefUpdateKind, parse_porcelain};
+use crate::remotes::{Remote, RemoteSet};
+use crate::report::{RemoteOutcome, failed_remotes, write_outcomes};
+use crate::retry::{FailureKind, classify_failure};
+
+/// Refs relayed when none are selected: every branch and every tag.
+pub const DEFAULT_RELAY_REFS: &[&str] = &["refs/heads/*", "refs/tags/*"];
+
+/// Settings for a single `relay` run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayOptions {
+    /// Bare repository holding the fetched refs between runs; created when
+    /// missing.
+    pub cache: PathBuf,
+    /// Remote to fetch from. Defaults to [`RemoteSet::primary`].
+    pub source: Option<String>,
+    /// Ref patterns to relay, such as `refs/heads/*` or `refs/tags/v*`.
+    /// Empty means [`DEFAULT_RELAY_REFS`].
+    pub refs: Vec<String>,
+    /// Delete refs matching the patterns from the destinations when the
+    /// source no longer has them.
+    pub prune: bool,
+    /// Preview each destination's ref updates with `git push --dry-run`.
+    pub dry_run: bool,
+}
+
+/// How relaying to one destination went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayResult {
+    pub remote: String,
+    pub refs: Vec<RefUpdate>,
+    /// Why the push failed; `None` when it succeeded.
+    pub failure: Option<FailureKind>,
+    /// git's error output for a failed push.
+    pub message: String,
+}
+
+impl RelayResult {
+    pub fn is_success(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl RemoteOutcome for RelayResult {
+    fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    fn failure(&self) -> Option<FailureKind> {
+        self.failure
+    }
+
+    fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// The outcome of one relay run, with one result per destination in the
+/// order of the remote set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayReport {
+    pub source: String,
+    pub dry_run: bool,
+    /// The refs the fetch from the source updated in the cache, as git
+    /// lists them.
+    pub fetched: Vec<String>,
+    pub results: Vec<RelayResult>,
+}
+
+impl RelayReport {
+    /// Turns the report into an error when any destination failed.
+    pub fn check(&self) -> GitResult<()> {
+        match failed_remotes(&self.results) {
+            None => Ok(()),
+            Some((remotes, partial)) => Err(GitError::PushFailed { remotes, partial }),
+        }
+    }
+}
+
+impl fmt::Display for RelayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = if self.dry_run {
+            "Dry run of relay"
+        } else {
+            "Relay report"
+        };
+        writeln!(f, "{} from {}:", title, self.source)?;
+        write_outcomes(
+            f,
+            &self.results,
+            |_| "success",
+            |result| {
+                result
+                    .refs
+                    .iter()
+                    .filter(|update| update.kind != RefUpdateKind::UpToDate)
+                    .map(RefUpdate::to_string)
+                    .collect()
+            },
+        )
+    }
+}
+
+/// Copies refs from the source remote to every other remote in `remotes`
+/// through the bare repository `options.cache`, without needing a work
+/// tree. The cache is fetched into first, pruning refs the source deleted,
+/// then pushed to the destinations in parallel. Pushes are not forced, so a
+/// destination that has diverged from the source is reported as rejected.
+pub fn relay(remotes: &RemoteSet, options: &RelayOptions) -> GitResult<RelayReport> {
+    let patterns: Vec<String> = if options.refs.is_empty() {
+        DEFAULT_RELAY_REFS
+            .iter()
+            .map(|ref_| ref_.to_string())
+            .collect()
+    } else {
+        options.refs.clone()
+    };
+    for pattern in &patterns {
+        validate_pattern(pattern)?;
+    }
+
+    let source = match &options.source {
+        Some(name) => remotes
+            .get(name)
+            .ok_or_else(|| GitError::Usage(format!("Remote '{}' is not one of --remotes", name)))?,
+        None => remotes
+            .primary()
+            .ok_or_else(|| GitError::Usage("No remotes to relay from".to_string()))?,
+    };
+    let destinations: Vec<(&Remote, String)> = remotes
+        .iter()
+        .filter(|remote| remote.name != source.name)
+        .map(|remote| Ok((remote, remote_url(remote)?)))
+        .collect::<GitResult<_>>()?;
+    if destinations.is_empty() {
+        return Err(GitError::Usage(format!(
+            "Relaying needs a destination remote besides '{}'",
+            source.name
+        )));
+    }
+
+    prepare_cache(&options.cache)?;
+
+    let refspecs: Vec<String> = patterns
+        .iter()
+        .map(|pattern| format!("{}:{}", pattern, pattern))
+        .collect();
+
+    let mut fetch = git_in(&options.cache);
+    fetch
+        .args(["fetch", "--prune", "--no-tags", &remote_url(source)?])
+        .args(refspecs.iter().map(|refspec| format!("+{}", refspec)));
+    let run = run_with_timeout(&mut fetch, source.timeout)?;
+    if run.timed_out {
+        return Err(GitError::Timeout {
+            remote: source.name.clone(),
+            after: run.elapsed,
+        });
+    }
+    let stderr = String::from_utf8_lossy(&run.output.stderr);
+    if !run.output.status.success() {
+        return Err(GitError::CommandFailed(format!(
+            "Could not fetch from {}: {}",
+            source.name,
+            stderr.trim()
+        )));
+    }
+    let fetched = fetch_updates(&stderr);
+    println!(
+        "Fetched {} into {}: {} ref(s) updated.",
+        source.name,
+        options.cache.display(),
+        fetched.len()
+    );
+
+    let results = run_parallel(&destinations, destinations.len(), |(remote, url)| {
+        push_from_cache(remote, url, &refspecs, options)
+    });
+
+    let report = RelayReport {
+        source: source.name.clone(),
+        dry_run: options.dry_run,
+        fetched,
+        results,
+    };
+    print!("{}", report);
+    Ok(report)
+}
+
+/// The URL configured for `remote`, or failing that the URL of the remote
+/// with the same name in the current repository, if there is one.
+fn remote_url(remote: &Remote) -> GitResult<String> {
+    remote
+        .url
+        .clone()
+        .or_else(|| get_remote_url(&remote.name))
+        .ok_or_else(|| {
+            GitError::Usage(format!(
+                "No URL for remote '{0}'. Pass --remote {0}=<URL> or set {1}.",
+                remote.name,
+                url_env_var(&remote.name)
+            ))
+        })
+}
+
+/// Refspec sides must be full ref names, and git allows at most one `*`.
+fn validate_pattern(pattern: &str) -> GitResult<()> {
+    if !pattern.starts_with("refs/") || pattern.matches('*').count() > 1 {
+        return Err(GitError::Usage(format!(
+            "Invalid ref pattern '{}': expected a ref name starting with refs/ and at most one '*'",
+            pattern
+        )));
+    }
+
+    Ok(())
+}
+
+/// Creates the bare cache repository, or checks that an existing one is
+/// bare.
+fn prepare_cache(cache: &Path) -> GitResult<()> {
+    if cache.exists() {
+        if !is_bare_repo(cache) {
+            return Err(GitError::Usage(format!(
+                "'{}' exists but is not a bare git repository",
+                cache.display()
+            )));
+        }
+        return Ok(());
+    }
+
+    let output = Command::new("git")
+        .args(["init", "--bare", "--quiet"])
+        .arg(cache)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitError::CommandFailed(format!(
+            "Error creating relay cache '{}': {}",
+            cache.display(),
+            stderr
+        )));
+    }
+
+    println!("Created relay cache at {}.", cache.display());
+    Ok(())
+}
+
+fn push_from_cache(
+    remote: &Remote,
+    url: &str,
+    refspecs: &[String],
+    options: &RelayOptions,
+) -> RelayResult {
+    let mut result = RelayResult {
+        remote: remote.name.clone(),
+        refs: Vec::new(),
+        failure: None,
+        message: String::new(),
+    };
+
+    let mut push = git_in(&options.cache);
+    push.args(["push", "--porcelain"]);
+    if options.prune {
+        push.arg("--prune");
+    }
+    if options.dry_run {
+        push.arg("--dry-run");
+    }
+    push.arg(url).args(refspecs);
+
+    let run = match run_with_timeout(&mut push, remote.timeout) {
+        Ok(run) => run,
+        Err(e) => {
+            result.failure = Some(FailureKind::Other);
+            result.message = e.to_string();
+            return result;
+        }
+    };
+
+    result.refs = parse_porcelain(&String::from_utf8_lossy(&run.output.stdout));
+    if run.timed_out {
+        result.failure = Some(FailureKind::Timeout);
+        result.message = GitError::Timeout {
+            remote: remote.name.clone(),
+            after: run.elapsed,
+        }
+        .to_string();
+    } else if !run.output.status.success() {
+        let stderr = String::from_utf8_lossy(&run.output.stderr);
+        result.failure = Some(
+            if result.refs.iter().any(|update| update.kind.is_rejected()) {
+                FailureKind::Rejected
+            } else {
+                classify_failure(&stderr)
+            },
+        );
+        // Rejected refs are already listed with their reasons.
+        if result.failure != Some(FailureKind::Rejected) {
+            result.message = stderr.trim().to_string();
+        }
+    }
+
+    result
+}
+
+/// A git command working on the bare repository `cache`, whatever the
+/// current directory is.
+fn git_in(cache: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.arg("--git-dir").arg(cache);
+    command
+}
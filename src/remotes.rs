@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::git_helpers::{GitError, GitResult};
+
+/// Remote names used when nothing else is specified.
+pub const DEFAULT_REMOTE_NAMES: [&str; 2] = ["origin", "mirror"];
+
+/// Whether a remote is authoritative or a best-effort copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteRole {
+    Primary,
+    Secondary,
+}
+
+impl RemoteRole {
+    /// Role a remote gets when none is configured: `origin` is primary,
+    /// everything else secondary.
+    pub fn default_for(name: &str) -> Self {
+        if name == "origin" {
+            RemoteRole::Primary
+        } else {
+            RemoteRole::Secondary
+        }
+    }
+}
+
+/// How a remote is pushed to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteMode {
+    /// The selected branches and tags are pushed.
+    #[default]
+    Branch,
+    /// Every branch, tag and note is replicated, and refs that no longer
+    /// exist locally are deleted on the remote.
+    Mirror,
+}
+
+/// A single named remote, optionally carrying the URL it should point at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    pub name: String,
+    pub url: Option<String>,
+    pub role: RemoteRole,
+    pub mode: RemoteMode,
+    /// Branch patterns (`*` and `?` wildcards) this remote receives.
+    /// An empty list means every branch.
+    pub branches: Vec<String>,
+    /// Values forwarded to `git push --push-option`.
+    pub push_options: Vec<String>,
+    /// How long git may run against this remote before it is killed.
+    pub timeout: Option<Duration>,
+}
+
+impl Remote {
+    pub fn new(name: impl Into<String>, url: Option<String>) -> Self {
+        let name = name.into();
+        Remote {
+            role: RemoteRole::default_for(&name),
+            mode: RemoteMode::Branch,
+            name,
+            url,
+            branches: Vec::new(),
+            push_options: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    pub fn is_mirror(&self) -> bool {
+        self.mode == RemoteMode::Mirror
+    }
+
+    /// Whether `branch` should be pushed to this remote.
+    pub fn accepts_branch(&self, branch: &str) -> bool {
+        self.branches.is_empty()
+            || self
+                .branches
+                .iter()
+                .any(|pattern| glob_match(pattern, branch))
+    }
+}
+
+/// An ordered set of uniquely named remotes that andiamo manages.
+///
+/// The order is significant: remotes are added and pushed in the order
+/// they were inserted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteSet {
+    remotes: Vec<Remote>,
+}
+
+impl RemoteSet {
+    pub fn new() -> Self {
+        RemoteSet::default()
+    }
+
+    /// Builds the classic `origin` + `mirror` pair without URLs.
+    pub fn with_defaults() -> Self {
+        RemoteSet::from_names(DEFAULT_REMOTE_NAMES).expect("default remote names are valid")
+    }
+
+    /// Builds a set from remote names, without URLs.
+    pub fn from_names<I, S>(names: I) -> GitResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut set = RemoteSet::new();
+        for name in names {
+            set.insert(Remote::new(name, None))?;
+        }
+        Ok(set)
+    }
+
+    /// Adds a remote to the end of the set.
+    ///
+    /// Fails if the name is not a usable git remote name or is already present.
+    pub fn insert(&mut self, remote: Remote) -> GitResult<()> {
+        validate_remote_name(&remote.name)?;
+
+        if self.contains(&remote.name) {
+            return Err(GitError::RemoteAlreadyExists(remote.name));
+        }
+
+        self.remotes.push(remote);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Remote> {
+        self.remotes.iter().find(|remote| remote.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Remote> {
+        self.remotes.iter_mut().find(|remote| remote.name == name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// The remote that pulls, relays and upstreams default to: the first
+    /// primary remote, or the first remote when none is primary.
+    pub fn primary(&self) -> Option<&Remote> {
+        self.remotes
+            .iter()
+            .find(|remote| remote.role == RemoteRole::Primary)
+            .or_else(|| self.remotes.first())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.remotes
+            .iter()
+            .map(|remote| remote.name.as_str())
+            .collect()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Remote> {
+        self.remotes.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Remote> {
+        self.remotes.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.remotes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remotes.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a RemoteSet {
+    type Item = &'a Remote;
+    type IntoIter = std::slice::Iter<'a, Remote>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub(crate) fn validate_remote_name(name: &str) -> GitResult<()> {
+    let invalid = name.is_empty()
+        || name.starts_with('-')
+        || name.chars().any(|c| c.is_whitespace() || c.is_control())
+        || name.contains(['\\', ':', '?', '*', '[', '~', '^'])
+        || name.contains("..");
+
+    if invalid {
+        return Err(GitError::InvalidRemoteName(name.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Matches `text` against a pattern where `*` matches any run of characters
+/// and `?` matches exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
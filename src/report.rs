@@ -0,0 +1,63 @@
+use std::fmt;
+
+use crate::retry::FailureKind;
+
+/// One remote's entry in a fetch or relay report.
+pub(crate) trait RemoteOutcome {
+    fn remote(&self) -> &str;
+    /// Why the remote failed; `None` when it succeeded.
+    fn failure(&self) -> Option<FailureKind>;
+    /// git's error output for a failure.
+    fn message(&self) -> &str;
+}
+
+/// The width of a report's remote column: the length of the longest name.
+pub(crate) fn remote_width<'a>(names: impl IntoIterator<Item = &'a str>) -> usize {
+    names.into_iter().map(str::len).max().unwrap_or(0)
+}
+
+/// The remotes in `results` that failed, and whether others succeeded;
+/// `None` when none failed.
+pub(crate) fn failed_remotes<T: RemoteOutcome>(results: &[T]) -> Option<(Vec<String>, bool)> {
+    let failed: Vec<String> = results
+        .iter()
+        .filter(|result| result.failure().is_some())
+        .map(|result| result.remote().to_string())
+        .collect();
+
+    if failed.is_empty() {
+        None
+    } else {
+        let partial = failed.len() < results.len();
+        Some((failed, partial))
+    }
+}
+
+/// Writes one aligned line per remote, saying `failed [<kind>]` or what
+/// `status` returns, with the remote's `details` and error output indented
+/// below it.
+pub(crate) fn write_outcomes<T: RemoteOutcome>(
+    f: &mut fmt::Formatter<'_>,
+    results: &[T],
+    status: impl Fn(&T) -> &'static str,
+    details: impl Fn(&T) -> Vec<String>,
+) -> fmt::Result {
+    let width = remote_width(results.iter().map(RemoteOutcome::remote));
+
+    for result in results {
+        let status = match result.failure() {
+            Some(failure) => format!("failed [{}]", failure.label()),
+            None => status(result).to_string(),
+        };
+        writeln!(f, "  {:width$}  {}", result.remote(), status, width = width)?;
+
+        for line in details(result) {
+            writeln!(f, "      {}", line)?;
+        }
+        for line in result.message().lines() {
+            writeln!(f, "      {}", line)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::config::RetryConfig;
+
+/// Why a git network operation failed, as far as its error output tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Network trouble or a timeout; trying again may help.
+    Transient,
+    /// The remote refused the update, e.g. a non-fast-forward or a hook.
+    Rejected,
+    /// Credentials were missing or refused.
+    Auth,
+    /// Anything else, such as a missing repository.
+    Other,
+    /// git did not finish within the configured timeout and was killed.
+    Timeout,
+    /// A `--force-with-lease` push found the remote ref somewhere other
+    /// than its remote-tracking ref said.
+    StaleLease,
+    /// `--atomic` was requested but the remote does not advertise support
+    /// for atomic pushes.
+    AtomicUnsupported,
+    /// The push went through, but the remote's refs did not match what was
+    /// pushed afterwards, or could not be checked.
+    Verification,
+}
+
+impl FailureKind {
+    pub fn is_transient(self) -> bool {
+        matches!(self, FailureKind::Transient | FailureKind::Timeout)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FailureKind::Transient => "network",
+            FailureKind::Rejected => "rejected",
+            FailureKind::Auth => "auth",
+            FailureKind::Other => "error",
+            FailureKind::Timeout => "timeout",
+            FailureKind::StaleLease => "stale lease",
+            FailureKind::AtomicUnsupported => "no atomic support",
+            FailureKind::Verification => "verification",
+        }
+    }
+}
+
+const AUTH_PATTERNS: &[&str] = &[
+    "authentication failed",
+    "permission denied",
+    "could not read username",
+    "could not read password",
+    "invalid username or password",
+    "terminal prompts disabled",
+    "access denied",
+    "requested url returned error: 401",
+    "requested url returned error: 403",
+];
+
+const REJECTED_PATTERNS: &[&str] = &[
+    "[rejected]",
+    "[remote rejected]",
+    "non-fast-forward",
+    "fetch first",
+    "hook declined",
+    "protected branch",
+];
+
+const TRANSIENT_PATTERNS: &[&str] = &[
+    "could not resolve host",
+    "could not resolve hostname",
+    "temporary failure in name resolution",
+    "connection timed out",
+    "operation timed out",
+    "timed out",
+    "connection refused",
+    "connection reset",
+    "connection closed",
+    "network is unreachable",
+    "no route to host",
+    "broken pipe",
+    "early eof",
+    "unexpected disconnect",
+    "the remote end hung up unexpectedly",
+    "rpc failed",
+    "requested url returned error: 429",
+    "requested url returned error: 500",
+    "requested url returned error: 502",
+    "requested url returned error: 503",
+    "requested url returned error: 504",
+];
+
+/// Classifies a failed push or fetch from git's error output.
+///
+/// Authentication problems are checked first, because git follows them
+/// with generic messages such as "the remote end hung up unexpectedly" that
+/// would otherwise look like network trouble. A failed lease is told apart
+/// from other rejections by git's "stale info" reason, and a remote
+/// without atomic push support from the network error git reports next.
+pub fn classify_failure(stderr: &str) -> FailureKind {
+    let stderr = stderr.to_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|pattern| stderr.contains(pattern));
+
+    if matches(AUTH_PATTERNS) {
+        FailureKind::Auth
+    } else if stderr.contains("does not support --atomic") {
+        FailureKind::AtomicUnsupported
+    } else if stderr.contains("stale info") {
+        FailureKind::StaleLease
+    } else if matches(REJECTED_PATTERNS) {
+        FailureKind::Rejected
+    } else if matches(TRANSIENT_PATTERNS) {
+        FailureKind::Transient
+    } else {
+        FailureKind::Other
+    }
+}
+
+/// How often and how patiently transient failures are retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts after the first one.
+    pub retries: u32,
+    /// Delay before the first retry; doubled for every further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_backoff: Duration,
+    /// Fraction of each delay, between 0 and 1, that is randomised.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 2,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &RetryConfig) -> Self {
+        let defaults = RetryPolicy::default();
+
+        RetryPolicy {
+            retries: config.retries.unwrap_or(defaults.retries),
+            initial_backoff: config
+                .backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.initial_backoff),
+            max_backoff: config
+                .max_backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.max_backoff),
+            jitter: config.jitter.unwrap_or(defaults.jitter),
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 1), without jitter.
+    pub fn base_delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Delay before retry number `retry` with up to `jitter` of it
+    /// subtracted at random, so that parallel retries spread out.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let base = self.base_delay(retry);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        base.mul_f64(1.0 - jitter * random_fraction())
+    }
+}
+
+/// A random number in `[0, 1)`, drawn from the randomly seeded std hasher.
+fn random_fraction() -> f64 {
+    let value = RandomState::new().build_hasher().finish();
+    (value >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,268 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::fetch::{FetchError, fetch_remotes};
+use crate::git_helpers::{
+    GitError, GitResult, ahead_behind, count_commits, current_branch, get_remote_url, is_git_repo,
+    local_branches, resolve_ref, unborn_branch,
+};
+use crate::remotes::{Remote, RemoteSet};
+use crate::report::remote_width;
+
+/// Settings for a single `collect_status` run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusOptions {
+    /// Report every local branch instead of just the current one.
+    pub all_branches: bool,
+    /// Fetch every remote first instead of using the remote-tracking refs
+    /// as they are.
+    pub fetch: bool,
+}
+
+/// How every remote compares with the local branches, as shown by
+/// `andiamo status`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusReport {
+    /// Whether the remotes were fetched, rather than cached remote-tracking
+    /// refs used.
+    pub fetched: bool,
+    /// Remotes that could not be fetched; their cached refs are used.
+    pub fetch_errors: Vec<FetchError>,
+    /// The commit HEAD points at when it is detached.
+    pub detached_head: Option<String>,
+    pub branches: Vec<BranchStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BranchStatus {
+    pub branch: String,
+    /// The local commit; `None` for a branch without commits yet.
+    pub local: Option<String>,
+    /// One entry per remote, in the order of the remote set.
+    pub remotes: Vec<RemoteBranchStatus>,
+    /// Every pair of remotes that both have the branch.
+    pub between_remotes: Vec<RemoteComparison>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemoteBranchStatus {
+    pub remote: String,
+    #[serde(flatten)]
+    pub state: RemoteState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "kebab-case")]
+pub enum RemoteState {
+    /// The remote is not added to the repository.
+    NotAdded,
+    /// There is no remote-tracking branch for this branch.
+    Missing,
+    /// The remote-tracking branch points at `sha`. `ahead` counts the
+    /// commits the remote has that local does not, `behind` the local
+    /// commits the remote lacks.
+    Tracked {
+        sha: String,
+        ahead: usize,
+        behind: usize,
+    },
+}
+
+impl RemoteState {
+    pub fn is_in_sync(&self) -> bool {
+        matches!(
+            self,
+            RemoteState::Tracked {
+                ahead: 0,
+                behind: 0,
+                ..
+            }
+        )
+    }
+}
+
+/// How the branch on `remote` compares with the same branch on `other`:
+/// `ahead` commits only `remote` has and `behind` commits only `other` has.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemoteComparison {
+    pub remote: String,
+    pub other: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// Compares the current branch, or every local branch with
+/// `options.all_branches`, with its remote-tracking branch on each remote
+/// and the remotes with each other. With `options.fetch` every added
+/// remote is fetched first, in parallel; remotes that cannot be fetched
+/// are listed in [`StatusReport::fetch_errors`] and compared from their
+/// cached refs.
+pub fn collect_status(remotes: &RemoteSet, options: &StatusOptions) -> GitResult<StatusReport> {
+    if !is_git_repo() {
+        return Err(GitError::NotAGitRepository);
+    }
+
+    let added: Vec<&Remote> = remotes
+        .iter()
+        .filter(|remote| get_remote_url(&remote.name).is_some())
+        .collect();
+
+    let fetch_errors = if options.fetch {
+        fetch_remotes(&added)
+    } else {
+        Vec::new()
+    };
+
+    let (detached_head, branches) = match current_branch() {
+        Err(GitError::DetachedHead) => {
+            let branches = if options.all_branches {
+                local_branches()?
+            } else {
+                Vec::new()
+            };
+            (resolve_ref("HEAD"), branches)
+        }
+        Err(e) => {
+            let unborn = unborn_branch().ok_or(e)?;
+            let mut branches = if options.all_branches {
+                local_branches()?
+            } else {
+                Vec::new()
+            };
+            branches.push(unborn);
+            (None, branches)
+        }
+        Ok(_) if options.all_branches => (None, local_branches()?),
+        Ok(branch) => (None, vec![branch]),
+    };
+
+    let branches = branches
+        .iter()
+        .map(|branch| branch_status(remotes, &added, branch))
+        .collect::<GitResult<Vec<BranchStatus>>>()?;
+
+    Ok(StatusReport {
+        fetched: options.fetch,
+        fetch_errors,
+        detached_head,
+        branches,
+    })
+}
+
+fn branch_status(remotes: &RemoteSet, added: &[&Remote], branch: &str) -> GitResult<BranchStatus> {
+    let local_ref = format!("refs/heads/{}", branch);
+    let local = resolve_ref(&local_ref);
+
+    let mut statuses = Vec::new();
+    let mut tracked = Vec::new();
+    for remote in remotes {
+        let state = if !added.iter().any(|added| added.name == remote.name) {
+            RemoteState::NotAdded
+        } else {
+            let tracking = format!("refs/remotes/{}/{}", remote.name, branch);
+            match resolve_ref(&tracking) {
+                None => RemoteState::Missing,
+                Some(sha) => {
+                    let (ahead, behind) = match local {
+                        Some(_) => ahead_behind(&tracking, &local_ref)?,
+                        None => (count_commits(&tracking)?, 0),
+                    };
+                    tracked.push((remote.name.as_str(), tracking));
+                    RemoteState::Tracked { sha, ahead, behind }
+                }
+            }
+        };
+        statuses.push(RemoteBranchStatus {
+            remote: remote.name.clone(),
+            state,
+        });
+    }
+
+    let mut between_remotes = Vec::new();
+    for (index, (other, other_ref)) in tracked.iter().enumerate() {
+        for (remote, remote_ref) in &tracked[index + 1..] {
+            let (ahead, behind) = ahead_behind(remote_ref, other_ref)?;
+            between_remotes.push(RemoteComparison {
+                remote: remote.to_string(),
+                other: other.to_string(),
+                ahead,
+                behind,
+            });
+        }
+    }
+
+    Ok(BranchStatus {
+        branch: branch.to_string(),
+        local,
+        remotes: statuses,
+        between_remotes,
+    })
+}
+
+/// Describes `ahead`/`behind` counts relative to `what`, e.g.
+/// `2 behind local` or `in sync with origin`.
+fn describe(ahead: usize, behind: usize, what: &str) -> String {
+    match (ahead, behind) {
+        (0, 0) => format!("in sync with {}", what),
+        (ahead, 0) => format!("{} ahead of {}", ahead, what),
+        (0, behind) => format!("{} behind {}", behind, what),
+        (ahead, behind) => format!("{} ahead of, {} behind {}", ahead, behind, what),
+    }
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(sha) = &self.detached_head {
+            writeln!(f, "HEAD detached at {}", &sha[..sha.len().min(7)])?;
+        }
+
+        if !self.fetched && !self.branches.is_empty() {
+            writeln!(
+                f,
+                "Using cached remote-tracking refs; pass --fetch to update them."
+            )?;
+        }
+        for error in &self.fetch_errors {
+            writeln!(
+                f,
+                "Could not fetch {}, using cached refs: {}",
+                error.remote, error.message
+            )?;
+        }
+
+        for branch in &self.branches {
+            match branch.local {
+                Some(_) => writeln!(f, "On branch {}", branch.branch)?,
+                None => writeln!(f, "On branch {} (no commits yet)", branch.branch)?,
+            }
+
+            let width = remote_width(branch.remotes.iter().map(|status| status.remote.as_str()));
+            for status in &branch.remotes {
+                let state = match &status.state {
+                    RemoteState::NotAdded => "not added".to_string(),
+                    RemoteState::Missing => "no remote-tracking branch".to_string(),
+                    RemoteState::Tracked { ahead, behind, .. } => {
+                        describe(*ahead, *behind, "local")
+                    }
+                };
+                writeln!(f, "  {:width$}  {}", status.remote, state, width = width)?;
+            }
+
+            for comparison in branch
+                .between_remotes
+                .iter()
+                .filter(|comparison| comparison.ahead > 0 || comparison.behind > 0)
+            {
+                writeln!(
+                    f,
+                    "  {} is {}",
+                    comparison.remote,
+                    describe(comparison.ahead, comparison.behind, &comparison.other)
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
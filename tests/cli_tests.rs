@@ -1,69 +1,99 @@
-use andiamo::cli::Cli;
+use andiamo::cli::{
+    Cli, Command, FetchArgs, PullArgs, PushArgs, ReconcileArgs, RelayArgs, RemoteCommand,
+    RemoteUrlArgs, parse_remote_spec,
+};
+use andiamo::push::{BranchSelection, TagMode};
+use andiamo::reconcile::ReconcileStrategy;
+use clap::Parser;
 
 #[test]
 fn test_cli_default_values() {
     let cli = Cli {
+        command: None,
         init: false,
         add_remotes: false,
         push: false,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
-    assert_eq!(cli.init, false);
-    assert_eq!(cli.add_remotes, false);
-    assert_eq!(cli.push, false);
+    assert!(!cli.init);
+    assert!(!cli.add_remotes);
+    assert!(!cli.push);
 }
 
 #[test]
 fn test_cli_with_init() {
     let cli = Cli {
+        command: None,
         init: true,
         add_remotes: false,
         push: false,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
-    assert_eq!(cli.init, true);
-    assert_eq!(cli.add_remotes, false);
-    assert_eq!(cli.push, false);
+    assert!(cli.init);
+    assert!(!cli.add_remotes);
+    assert!(!cli.push);
 }
 
 #[test]
 fn test_cli_with_all_flags() {
     let cli = Cli {
+        command: None,
         init: true,
         add_remotes: true,
         push: true,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
-    assert_eq!(cli.init, true);
-    assert_eq!(cli.add_remotes, true);
-    assert_eq!(cli.push, true);
+    assert!(cli.init);
+    assert!(cli.add_remotes);
+    assert!(cli.push);
 }
 
 #[test]
 fn test_cli_partial_flags() {
     let cli = Cli {
+        command: None,
         init: false,
         add_remotes: true,
         push: true,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
-    assert_eq!(cli.init, false);
-    assert_eq!(cli.add_remotes, true);
-    assert_eq!(cli.push, true);
+    assert!(!cli.init);
+    assert!(cli.add_remotes);
+    assert!(cli.push);
 }
 
 #[test]
 fn test_cli_equality() {
     let cli1 = Cli {
+        command: None,
         init: true,
         add_remotes: false,
         push: false,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
     let cli2 = Cli {
+        command: None,
         init: true,
         add_remotes: false,
         push: false,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
     assert_eq!(cli1, cli2);
@@ -72,9 +102,13 @@ fn test_cli_equality() {
 #[test]
 fn test_cli_clone() {
     let cli1 = Cli {
+        command: None,
         init: true,
         add_remotes: true,
         push: false,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
     let cli2 = cli1.clone();
@@ -85,9 +119,13 @@ fn test_cli_clone() {
 #[test]
 fn test_cli_debug() {
     let cli = Cli {
+        command: None,
         init: true,
         add_remotes: false,
         push: true,
+        remotes: None,
+        profile: None,
+        urls: RemoteUrlArgs::default(),
     };
 
     let debug_str = format!("{:?}", cli);
@@ -95,3 +133,304 @@ fn test_cli_debug() {
     assert!(debug_str.contains("add_remotes: false"));
     assert!(debug_str.contains("push: true"));
 }
+
+#[test]
+fn test_cli_parse_default_remotes() {
+    let cli = Cli::parse_from(["andiamo", "--push"]);
+
+    assert!(cli.push);
+    assert_eq!(cli.remotes, None);
+}
+
+#[test]
+fn test_cli_parse_custom_remotes() {
+    let cli = Cli::parse_from([
+        "andiamo",
+        "--push",
+        "--remotes",
+        "origin,codeberg,nas-backup",
+    ]);
+
+    assert_eq!(
+        cli.remotes,
+        Some(vec![
+            "origin".to_string(),
+            "codeberg".to_string(),
+            "nas-backup".to_string()
+        ])
+    );
+}
+
+#[test]
+fn test_cli_parse_push_subcommand() {
+    let cli = Cli::parse_from(["andiamo", "push", "--remotes", "origin,codeberg"]);
+
+    assert_eq!(cli.command, Some(Command::Push(PushArgs::default())));
+    assert!(!cli.uses_legacy_flags());
+    assert_eq!(
+        cli.remotes,
+        Some(vec!["origin".to_string(), "codeberg".to_string()])
+    );
+}
+
+#[test]
+fn test_cli_parse_remote_subcommands() {
+    let cli = Cli::parse_from([
+        "andiamo",
+        "remote",
+        "add",
+        "codeberg",
+        "https://codeberg.org/a/b.git",
+    ]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Remote {
+            command: RemoteCommand::Add {
+                name: Some("codeberg".to_string()),
+                url: Some("https://codeberg.org/a/b.git".to_string()),
+                dry_run: false,
+            }
+        })
+    );
+
+    let cli = Cli::parse_from(["andiamo", "remote", "set-url", "mirror", "https://x/y.git"]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Remote {
+            command: RemoteCommand::SetUrl {
+                name: "mirror".to_string(),
+                url: "https://x/y.git".to_string(),
+            }
+        })
+    );
+
+    let cli = Cli::parse_from(["andiamo", "remote", "list"]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Remote {
+            command: RemoteCommand::List
+        })
+    );
+}
+
+#[test]
+fn test_cli_remote_add_name_requires_url() {
+    let result = Cli::try_parse_from(["andiamo", "remote", "add", "codeberg"]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_cli_legacy_flags_conflict_with_subcommands() {
+    let result = Cli::try_parse_from(["andiamo", "--push", "push"]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_cli_legacy_flags_still_parse() {
+    let cli = Cli::parse_from(["andiamo", "--init", "--add-remotes", "--push"]);
+
+    assert_eq!(cli.command, None);
+    assert!(cli.uses_legacy_flags());
+}
+
+#[test]
+fn test_parse_remote_spec() {
+    assert_eq!(
+        parse_remote_spec("codeberg=https://codeberg.org/a/b.git"),
+        Ok((
+            "codeberg".to_string(),
+            "https://codeberg.org/a/b.git".to_string()
+        ))
+    );
+    assert_eq!(
+        parse_remote_spec("nas=ssh://nas/x?a=b"),
+        Ok(("nas".to_string(), "ssh://nas/x?a=b".to_string()))
+    );
+    assert!(parse_remote_spec("codeberg").is_err());
+    assert!(parse_remote_spec("=url").is_err());
+    assert!(parse_remote_spec("name=").is_err());
+}
+
+#[test]
+fn test_cli_url_overrides_order() {
+    let cli = Cli::parse_from([
+        "andiamo",
+        "remote",
+        "add",
+        "--remote",
+        "codeberg=https://codeberg.org/a/b.git",
+        "--mirror-url",
+        "https://m/b.git",
+        "--origin-url",
+        "https://o/b.git",
+        "--non-interactive",
+    ]);
+
+    assert!(cli.urls.non_interactive);
+    let names: Vec<String> = cli
+        .urls
+        .overrides()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(names, vec!["origin", "mirror", "codeberg"]);
+}
+
+#[test]
+fn test_cli_push_jobs() {
+    let cli = Cli::parse_from(["andiamo", "push", "--jobs", "3"]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Push(PushArgs {
+            jobs: Some(3),
+            ..PushArgs::default()
+        }))
+    );
+
+    assert!(Cli::try_parse_from(["andiamo", "push", "--jobs", "0"]).is_err());
+}
+
+#[test]
+fn test_cli_push_no_check_divergence() {
+    let cli = Cli::parse_from(["andiamo", "push", "--no-check-divergence"]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Push(PushArgs {
+            no_check_divergence: true,
+            ..PushArgs::default()
+        }))
+    );
+
+    assert!(
+        Cli::try_parse_from([
+            "andiamo",
+            "push",
+            "--check-divergence",
+            "--no-check-divergence"
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn test_cli_push_tag_modes() {
+    let tag_mode = |args: &[&str]| match Cli::parse_from(args).command {
+        Some(Command::Push(args)) => args.tag_mode(),
+        other => panic!("expected push, got {:?}", other),
+    };
+
+    assert_eq!(tag_mode(&["andiamo", "push"]), TagMode::None);
+    assert_eq!(tag_mode(&["andiamo", "push", "--tags"]), TagMode::All);
+    assert_eq!(
+        tag_mode(&["andiamo", "push", "--follow-tags"]),
+        TagMode::Follow
+    );
+    assert_eq!(
+        tag_mode(&["andiamo", "push", "--tag", "v1.0"]),
+        TagMode::Single("v1.0".to_string())
+    );
+    assert!(Cli::try_parse_from(["andiamo", "push", "--tags", "--follow-tags"]).is_err());
+    assert!(Cli::try_parse_from(["andiamo", "push", "--tags", "--tag", "v1"]).is_err());
+}
+
+#[test]
+fn test_cli_push_branch_selection() {
+    let selection = |args: &[&str]| match Cli::parse_from(args).command {
+        Some(Command::Push(args)) => args.branch_selection(),
+        other => panic!("expected push, got {:?}", other),
+    };
+
+    assert_eq!(selection(&["andiamo", "push"]), BranchSelection::Current);
+    assert_eq!(
+        selection(&["andiamo", "push", "--all"]),
+        BranchSelection::All
+    );
+    assert_eq!(
+        selection(&[
+            "andiamo",
+            "push",
+            "--branch",
+            "release/*",
+            "--branch",
+            "main"
+        ]),
+        BranchSelection::Matching(vec!["release/*".to_string(), "main".to_string()])
+    );
+    assert_eq!(
+        selection(&["andiamo", "push", "--to-ref", "refs/heads/hotfix"]),
+        BranchSelection::HeadTo("hotfix".to_string())
+    );
+    assert!(Cli::try_parse_from(["andiamo", "push", "--to-ref", "hotfix"]).is_err());
+    assert!(Cli::try_parse_from(["andiamo", "push", "--to-ref", "refs/heads/x", "--all"]).is_err());
+    assert!(Cli::try_parse_from(["andiamo", "push", "--all", "--branch", "main"]).is_err());
+    assert!(Cli::try_parse_from(["andiamo", "push", "--branch", "main", "--tag", "v1"]).is_err());
+}
+
+#[test]
+fn test_cli_reconcile_strategies() {
+    let cli = Cli::parse_from([
+        "andiamo",
+        "reconcile",
+        "--strategy",
+        "review",
+        "--branch",
+        "develop",
+        "--dry-run",
+    ]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Reconcile(ReconcileArgs {
+            strategy: ReconcileStrategy::Review,
+            branch: Some("develop".to_string()),
+            yes: false,
+            dry_run: true,
+        }))
+    );
+
+    assert!(Cli::try_parse_from(["andiamo", "reconcile"]).is_err());
+    assert!(Cli::try_parse_from(["andiamo", "reconcile", "--strategy", "rebase"]).is_err());
+}
+
+#[test]
+fn test_cli_fetch_and_pull() {
+    let cli = Cli::parse_from(["andiamo", "fetch", "--prune"]);
+    assert_eq!(cli.command, Some(Command::Fetch(FetchArgs { prune: true })));
+
+    let cli = Cli::parse_from(["andiamo", "pull", "--failover"]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Pull(PullArgs { failover: true }))
+    );
+
+    let cli = Cli::parse_from(["andiamo", "pull"]);
+    assert_eq!(cli.command, Some(Command::Pull(PullArgs::default())));
+}
+
+#[test]
+fn test_cli_relay() {
+    let cli = Cli::parse_from([
+        "andiamo",
+        "relay",
+        "--cache",
+        "/var/cache/relay.git",
+        "--from",
+        "github",
+        "--ref",
+        "refs/heads/main",
+        "--ref",
+        "refs/tags/v*",
+        "--prune",
+    ]);
+    assert_eq!(
+        cli.command,
+        Some(Command::Relay(RelayArgs {
+            cache: "/var/cache/relay.git".into(),
+            from: Some("github".to_string()),
+            refs: vec!["refs/heads/main".to_string(), "refs/tags/v*".to_string()],
+            prune: true,
+            dry_run: false,
+        }))
+    );
+
+    assert!(Cli::try_parse_from(["andiamo", "relay"]).is_err());
+}
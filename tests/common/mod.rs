@@ -0,0 +1,207 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Helper function to run git commands in a specific directory
+pub fn run_git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", "Test User")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test User")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .env("GNUPGHOME", "/dev/null")
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                Ok(String::from_utf8_lossy(&output.stdout).to_string())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).to_string())
+            }
+        }
+        Err(e) => Err(format!("Failed to execute git: {}", e)),
+    }
+}
+
+/// A work tree plus a set of bare repositories registered as its remotes.
+pub struct Fixture {
+    pub root: TempDir,
+    pub work: PathBuf,
+}
+
+impl Fixture {
+    /// Creates a work tree on branch `main` with one commit and a bare
+    /// repository registered for each of `remotes`.
+    pub fn new(remotes: &[&str]) -> Self {
+        let root = TempDir::new().expect("Failed to create temp directory");
+        let work = root.path().join("work");
+        fs::create_dir(&work).expect("Failed to create work directory");
+
+        run_git(&work, &["init", "-b", "main"]).expect("Failed to init work tree");
+        run_git(&work, &["config", "user.name", "Test User"]).expect("Failed to set user");
+        run_git(&work, &["config", "user.email", "test@example.com"]).expect("Failed to set email");
+        run_git(&work, &["config", "commit.gpgsign", "false"]).expect("Failed to disable signing");
+
+        let fixture = Fixture { root, work };
+        fixture.commit("README.md", "hello\n", "Initial commit");
+
+        for name in remotes {
+            fixture.add_bare_remote(name);
+        }
+
+        fixture
+    }
+
+    /// Path of the bare repository backing the remote `name`.
+    pub fn remote_path(&self, name: &str) -> PathBuf {
+        self.root.path().join(format!("{}.git", name))
+    }
+
+    pub fn add_bare_remote(&self, name: &str) -> PathBuf {
+        let path = self.remote_path(name);
+        run_git(
+            self.root.path(),
+            &["init", "--bare", "-b", "main", path.to_str().unwrap()],
+        )
+        .expect("Failed to init bare remote");
+        run_git(&self.work, &["remote", "add", name, path.to_str().unwrap()])
+            .expect("Failed to add remote");
+        path
+    }
+
+    /// Installs an executable hook script in the bare repository of `remote`.
+    pub fn install_hook(&self, remote: &str, hook: &str, script: &str) {
+        let path = self.remote_path(remote).join("hooks").join(hook);
+        fs::write(&path, script).expect("Failed to write hook");
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_mode(0o755);
+        fs::set_permissions(&path, permissions).expect("Failed to make hook executable");
+    }
+
+    /// Points `remote` at its bare repository through `ssh://fake-host`, so
+    /// that pushes go through the `GIT_SSH_COMMAND` returned by
+    /// [`Fixture::ssh_wrapper`].
+    pub fn route_over_ssh(&self, remote: &str) {
+        let url = format!("ssh://fake-host{}", self.remote_path(remote).display());
+        run_git(&self.work, &["remote", "set-url", remote, &url]).expect("Failed to set URL");
+    }
+
+    /// Writes an ssh stand-in that runs `before` (shell code, which may exit)
+    /// and then executes git's remote command locally. `$n` holds how many
+    /// times the wrapper has been invoked, starting at 1.
+    pub fn ssh_wrapper(&self, before: &str) -> String {
+        let dir = self.root.path();
+        let path = dir.join("fake-ssh");
+        let script = format!(
+            "#!/bin/sh\n[ \"$1\" = -G ] && exit 0\nn=$(cat '{dir}/ssh-count' 2>/dev/null || echo 0)\nn=$((n+1))\necho $n > '{dir}/ssh-count'\n{before}\neval \"last=\\${{$#}}\"\nexec sh -c \"$last\"\n",
+            dir = dir.display(),
+            before = before
+        );
+        fs::write(&path, script).expect("Failed to write ssh wrapper");
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_mode(0o755);
+        fs::set_permissions(&path, permissions).expect("Failed to make wrapper executable");
+        path.display().to_string()
+    }
+
+    /// How many times the ssh wrapper has run.
+    pub fn ssh_invocations(&self) -> u32 {
+        fs::read_to_string(self.root.path().join("ssh-count"))
+            .ok()
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn commit(&self, file: &str, content: &str, message: &str) -> String {
+        fs::write(self.work.join(file), content).expect("Failed to write file");
+        run_git(&self.work, &["add", "."]).expect("Failed to stage");
+        run_git(&self.work, &["commit", "-m", message]).expect("Failed to commit");
+        self.head()
+    }
+
+    /// Commits on top of `start` and force-pushes that commit to `main` on
+    /// `remote` by path, as someone else would, so that this clone's
+    /// remote-tracking ref for `remote` does not see it until a fetch.
+    pub fn commit_behind_our_back(&self, remote: &str, start: &str, message: &str) -> String {
+        run_git(&self.work, &["checkout", "-q", "--detach", start]).expect("Failed to detach");
+        let sha = self.commit("theirs.txt", message, message);
+        let path = self.remote_path(remote);
+        run_git(
+            &self.work,
+            &["push", "-f", path.to_str().unwrap(), "HEAD:refs/heads/main"],
+        )
+        .expect("Failed to push by path");
+        run_git(&self.work, &["checkout", "-q", "main"]).expect("Failed to check out main");
+        sha
+    }
+
+    pub fn head(&self) -> String {
+        rev_parse(&self.work, "HEAD").expect("HEAD should resolve")
+    }
+
+    /// Runs the andiamo binary inside the work tree.
+    pub fn andiamo(&self, args: &[&str]) -> Output {
+        andiamo_in(&self.work, args, &[])
+    }
+
+    /// Runs the andiamo binary inside the work tree with extra environment.
+    pub fn andiamo_env(&self, args: &[&str], envs: &[(&str, &str)]) -> Output {
+        andiamo_in(&self.work, args, envs)
+    }
+}
+
+/// Resolves `rev` in `dir`, returning `None` if it does not exist.
+pub fn rev_parse(dir: &Path, rev: &str) -> Option<String> {
+    run_git(dir, &["rev-parse", "--verify", "--quiet", rev])
+        .ok()
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+}
+
+/// Runs the andiamo binary inside `dir`.
+pub fn andiamo_in(dir: &Path, args: &[&str], envs: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_andiamo"))
+        .current_dir(dir)
+        .args(args)
+        .envs(envs.iter().copied())
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", "Test User")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test User")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .env("GNUPGHOME", "/dev/null")
+        .output()
+        .expect("Failed to run andiamo")
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+/// Status column of `remote`'s line in a printed push report, e.g.
+/// `"success"` or `"skipped (reason)"`.
+pub fn report_status(report: &str, remote: &str) -> Option<String> {
+    report.lines().find_map(|line| {
+        let mut columns = line.split_whitespace();
+        if columns.next() != Some(remote) {
+            return None;
+        }
+        let role = columns.next()?;
+        if role != "primary" && role != "secondary" {
+            return None;
+        }
+        Some(columns.collect::<Vec<_>>().join(" "))
+    })
+}
@@ -0,0 +1,341 @@
+mod common;
+
+use andiamo::config::{GlobalConfig, RepoConfig, resolve_remotes};
+use andiamo::git_helpers::GitError;
+use andiamo::remotes::glob_match;
+use common::{Fixture, rev_parse, run_git, stderr};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const SAMPLE: &str = r#"
+[push]
+push_options = ["ci.skip"]
+
+[[remotes]]
+name = "origin"
+url = "https://gitlab.com/team/project.git"
+
+[[remotes]]
+name = "nas-backup"
+url = "ssh://nas.local/srv/git/project.git"
+branches = ["main", "release/*"]
+push_options = ["notify=false"]
+"#;
+
+fn parse(contents: &str) -> Result<RepoConfig, GitError> {
+    RepoConfig::parse(contents, Path::new(".andiamo.toml"))
+}
+
+fn config_message(result: Result<RepoConfig, GitError>) -> String {
+    match result {
+        Err(GitError::InvalidConfig { message, .. }) => message,
+        other => panic!("Expected InvalidConfig, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_sample_config() {
+    let config = parse(SAMPLE).expect("Sample config should parse");
+
+    assert_eq!(config.remotes.len(), 2);
+    assert_eq!(config.push.push_options, vec!["ci.skip"]);
+
+    let remotes = config.remote_set().expect("Remote set should build");
+    assert_eq!(remotes.names(), vec!["origin", "nas-backup"]);
+
+    let backup = remotes.get("nas-backup").unwrap();
+    assert_eq!(backup.push_options, vec!["ci.skip", "notify=false"]);
+    assert!(backup.accepts_branch("release/1.2"));
+    assert!(!backup.accepts_branch("feature/x"));
+    assert!(remotes.get("origin").unwrap().accepts_branch("feature/x"));
+}
+
+#[test]
+fn test_parse_rejects_unknown_fields() {
+    let message = config_message(parse("[[remotes]]\nname = \"origin\"\nulr = \"x\"\n"));
+    assert!(message.contains("ulr"), "message was: {}", message);
+}
+
+#[test]
+fn test_parse_rejects_duplicate_remotes() {
+    let message = config_message(parse(
+        "[[remotes]]\nname = \"origin\"\n\n[[remotes]]\nname = \"origin\"\n",
+    ));
+    assert!(message.contains("remotes[1] ('origin')"));
+    assert!(message.contains("duplicate"));
+}
+
+#[test]
+fn test_parse_rejects_empty_url() {
+    let message = config_message(parse("[[remotes]]\nname = \"origin\"\nurl = \" \"\n"));
+    assert!(message.contains("url must not be empty"));
+}
+
+#[test]
+fn test_parse_rejects_invalid_name() {
+    let message = config_message(parse("[[remotes]]\nname = \"my remote\"\n"));
+    assert!(message.contains("invalid remote name"));
+}
+
+#[test]
+fn test_remote_timeout_falls_back_to_push_timeout() {
+    let config = parse(
+        "[push]\ntimeout_secs = 60\n\n[[remotes]]\nname = \"origin\"\ntimeout_secs = 5\n\n[[remotes]]\nname = \"mirror\"\n",
+    )
+    .unwrap();
+    let remotes = config.remote_set().unwrap();
+
+    assert_eq!(
+        remotes.get("origin").unwrap().timeout,
+        Some(Duration::from_secs(5))
+    );
+    assert_eq!(
+        remotes.get("mirror").unwrap().timeout,
+        Some(Duration::from_secs(60))
+    );
+
+    let message = config_message(parse("[push]\ntimeout_secs = 0\n"));
+    assert!(message.contains("timeout_secs must be at least 1"));
+}
+
+#[test]
+fn test_parse_rejects_branches_in_mirror_mode() {
+    let message = config_message(parse(
+        "[[remotes]]\nname = \"backup\"\nmode = \"mirror\"\nbranches = [\"main\"]\n",
+    ));
+    assert!(message.contains("remotes[0] ('backup')"));
+    assert!(message.contains("mirror"));
+}
+
+#[test]
+fn test_resolve_remotes_precedence() {
+    let config = parse(SAMPLE).unwrap();
+
+    let from_config = resolve_remotes(None, Some(&config)).unwrap();
+    assert_eq!(from_config.names(), vec!["origin", "nas-backup"]);
+
+    let names = vec!["nas-backup".to_string(), "codeberg".to_string()];
+    let explicit = resolve_remotes(Some(&names), Some(&config)).unwrap();
+    assert_eq!(explicit.names(), vec!["nas-backup", "codeberg"]);
+    assert!(explicit.get("nas-backup").unwrap().url.is_some());
+    assert!(explicit.get("codeberg").unwrap().url.is_none());
+
+    let defaults = resolve_remotes(None, None).unwrap();
+    assert_eq!(defaults.names(), vec!["origin", "mirror"]);
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("release/*", "release/1.0"));
+    assert!(glob_match("*", "anything"));
+    assert!(glob_match("v?.*", "v1.2"));
+    assert!(!glob_match("release/*", "main"));
+    assert!(!glob_match("main", "main2"));
+}
+
+#[test]
+fn test_add_remotes_from_config_without_prompts() {
+    let fixture = Fixture::new(&[]);
+    let origin = fixture.root.path().join("origin.git");
+    let backup = fixture.root.path().join("backup.git");
+    for path in [&origin, &backup] {
+        run_git(
+            fixture.root.path(),
+            &["init", "--bare", path.to_str().unwrap()],
+        )
+        .unwrap();
+    }
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        format!(
+            "[[remotes]]\nname = \"origin\"\nurl = \"{}\"\n\n[[remotes]]\nname = \"backup\"\nurl = \"{}\"\nbranches = [\"release/*\"]\n",
+            origin.display(),
+            backup.display()
+        ),
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["sync"]);
+
+    assert!(
+        output.status.success(),
+        "andiamo failed: {}",
+        stderr(&output)
+    );
+    let remotes = run_git(&fixture.work, &["remote"]).unwrap();
+    assert!(remotes.lines().any(|line| line == "origin"));
+    assert!(remotes.lines().any(|line| line == "backup"));
+    assert_eq!(rev_parse(&origin, "refs/heads/main"), Some(fixture.head()));
+    assert_eq!(
+        rev_parse(&backup, "refs/heads/main"),
+        None,
+        "main does not match the backup remote's branch patterns"
+    );
+}
+
+#[test]
+fn test_invalid_config_reports_path() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[[remotes]]\nname = 3\n",
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains(".andiamo.toml"));
+}
+
+const PROFILES: &str = r#"
+[[profiles.work.remotes]]
+name = "origin"
+url = "git@gitlab.com:team/{repo}.git"
+
+[[profiles.work.remotes]]
+name = "mirror"
+url = "https://gitea.internal/team/{repo}.git"
+branches = ["main"]
+
+[[profiles.personal.remotes]]
+name = "origin"
+url = "git@github.com:me/{repo}.git"
+
+[[profiles.personal.remotes]]
+name = "codeberg"
+url = "git@codeberg.org:me/{repo}.git"
+"#;
+
+#[test]
+fn test_parse_global_profiles() {
+    let global = GlobalConfig::parse(PROFILES, Path::new("config.toml")).unwrap();
+
+    assert_eq!(global.profiles.len(), 2);
+    let personal = global.profile("personal").unwrap();
+    assert_eq!(personal.remotes[1].name, "codeberg");
+    assert!(global.profile("missing").is_none());
+}
+
+#[test]
+fn test_global_profile_validation_names_profile() {
+    let result = GlobalConfig::parse(
+        "[[profiles.work.remotes]]\nname = \"origin\"\n\n[[profiles.work.remotes]]\nname = \"origin\"\n",
+        Path::new("config.toml"),
+    );
+
+    match result {
+        Err(GitError::InvalidConfig { message, .. }) => {
+            assert!(
+                message.starts_with("profiles.work."),
+                "message was: {}",
+                message
+            )
+        }
+        other => panic!("Expected InvalidConfig, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_expand_templates() {
+    let global = GlobalConfig::parse(PROFILES, Path::new("config.toml")).unwrap();
+    let work = global
+        .profile("work")
+        .unwrap()
+        .expand_templates("andiamo")
+        .unwrap();
+
+    assert_eq!(
+        work.remotes[0].url.as_deref(),
+        Some("git@gitlab.com:team/andiamo.git")
+    );
+    assert_eq!(
+        work.remotes[1].url.as_deref(),
+        Some("https://gitea.internal/team/andiamo.git")
+    );
+
+    let bad = parse("[[remotes]]\nname = \"origin\"\nurl = \"git@host:{owner}/{repo}.git\"\n")
+        .unwrap()
+        .expand_templates("andiamo");
+    assert!(bad.is_err());
+}
+
+#[test]
+fn test_repo_config_overrides_profile() {
+    let global = GlobalConfig::parse(PROFILES, Path::new("config.toml")).unwrap();
+    let work = global
+        .profile("work")
+        .unwrap()
+        .expand_templates("andiamo")
+        .unwrap();
+    let repo = parse(
+        "[[remotes]]\nname = \"mirror\"\nurl = \"https://elsewhere/andiamo.git\"\n\n[[remotes]]\nname = \"nas\"\nurl = \"ssh://nas/andiamo.git\"\n",
+    )
+    .unwrap();
+
+    let merged = work.overlay(&repo);
+    let remotes = merged.remote_set().unwrap();
+
+    assert_eq!(remotes.names(), vec!["origin", "mirror", "nas"]);
+    let mirror = remotes.get("mirror").unwrap();
+    assert_eq!(mirror.url.as_deref(), Some("https://elsewhere/andiamo.git"));
+    assert_eq!(
+        mirror.branches,
+        vec!["main"],
+        "unset values keep the profile's"
+    );
+}
+
+#[test]
+fn test_add_remotes_with_profile() {
+    let fixture = Fixture::new(&[]);
+    let config_home = fixture.root.path().join("config");
+    fs::create_dir_all(config_home.join("andiamo")).unwrap();
+    let remotes_dir = fixture.root.path().join("hosts");
+    fs::write(
+        config_home.join("andiamo").join("config.toml"),
+        format!(
+            "[[profiles.work.remotes]]\nname = \"origin\"\nurl = \"{0}/gitlab/{{repo}}.git\"\n\n[[profiles.work.remotes]]\nname = \"mirror\"\nurl = \"{0}/gitea/{{repo}}.git\"\n",
+            remotes_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let output = fixture.andiamo_env(
+        &["remote", "add", "--profile", "work"],
+        &[("XDG_CONFIG_HOME", config_home.to_str().unwrap())],
+    );
+
+    assert!(
+        output.status.success(),
+        "andiamo failed: {}",
+        stderr(&output)
+    );
+    let origin_url = run_git(&fixture.work, &["remote", "get-url", "origin"]).unwrap();
+    let mirror_url = run_git(&fixture.work, &["remote", "get-url", "mirror"]).unwrap();
+    assert_eq!(
+        origin_url.trim(),
+        format!("{}/gitlab/work.git", remotes_dir.display())
+    );
+    assert_eq!(
+        mirror_url.trim(),
+        format!("{}/gitea/work.git", remotes_dir.display())
+    );
+}
+
+#[test]
+fn test_unknown_profile_fails() {
+    let fixture = Fixture::new(&[]);
+    let config_home = fixture.root.path().join("config");
+    fs::create_dir_all(config_home.join("andiamo")).unwrap();
+    fs::write(config_home.join("andiamo").join("config.toml"), PROFILES).unwrap();
+
+    let output = fixture.andiamo_env(
+        &["remote", "add", "--profile", "holiday"],
+        &[("XDG_CONFIG_HOME", config_home.to_str().unwrap())],
+    );
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("profile 'holiday' is not defined"));
+}
@@ -0,0 +1,193 @@
+mod common;
+
+use common::{Fixture, report_status, rev_parse, run_git, stderr, stdout};
+
+#[test]
+fn test_diverged_remotes_refuse_push() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    let base = fixture.head();
+    let theirs = fixture.commit_behind_our_back("mirror", &base, "Their commit");
+    let ours = fixture.commit("ours.txt", "ours", "Our commit");
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+    fixture.commit("next.txt", "next", "Next commit");
+
+    let output = fixture.andiamo(&["push", "--check-divergence"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(report.contains(&format!(
+        "Branch main has diverged between origin and mirror (merge-base {})",
+        &base[..7]
+    )));
+    assert!(report.contains(&format!("only on origin:\n    {} Our commit", &ours[..7])));
+    assert!(report.contains(&format!(
+        "only on mirror:\n    {} Their commit",
+        &theirs[..7]
+    )));
+    assert!(!report.contains("Push report"));
+    assert!(stderr(&output).contains("origin and mirror have diverged on main"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        Some(ours),
+        "origin should not have been pushed to"
+    );
+}
+
+#[test]
+fn test_divergence_check_is_on_unless_turned_off() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    let base = fixture.head();
+    let theirs = fixture.commit_behind_our_back("mirror", &base, "Their commit");
+    let ours = fixture.commit("ours.txt", "ours", "Our commit");
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+    let next = fixture.commit("next.txt", "next", "Next commit");
+
+    let refused = fixture.andiamo(&["push"]);
+    assert_eq!(refused.status.code(), Some(1));
+    assert!(stderr(&refused).contains("origin and mirror have diverged on main"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        Some(ours)
+    );
+
+    let output = fixture.andiamo(&["push", "--no-check-divergence"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        report_status(&stdout(&output), "mirror").as_deref(),
+        Some("failed [rejected]")
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        Some(next)
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(theirs)
+    );
+}
+
+#[test]
+fn test_remote_that_is_only_behind_is_not_divergence() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    fixture.commit("ours.txt", "ours", "Our commit");
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+
+    let output = fixture.andiamo(&["push", "--check-divergence"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(fixture.head())
+    );
+}
+
+#[test]
+fn test_unrelated_histories_are_divergence() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    run_git(&fixture.work, &["checkout", "-q", "--orphan", "unrelated"]).unwrap();
+    fixture.commit("other.txt", "other", "Unrelated root");
+    let mirror = fixture.remote_path("mirror");
+    run_git(
+        &fixture.work,
+        &["push", "-f", mirror.to_str().unwrap(), "unrelated:main"],
+    )
+    .unwrap();
+    run_git(&fixture.work, &["checkout", "-q", "-f", "main"]).unwrap();
+    std::fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[push]\ncheck_divergence = true\n",
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("(no common history)"));
+    assert!(stdout(&output).contains("Unrelated root"));
+}
+
+#[test]
+fn test_divergence_check_keeps_force_with_lease_protection() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    let base = fixture.head();
+
+    // Someone else pushes the same commit to both remotes.
+    let theirs = fixture.commit_behind_our_back("origin", &base, "Their commit");
+    let mirror_path = fixture.remote_path("mirror");
+    run_git(
+        &fixture.work,
+        &[
+            "push",
+            mirror_path.to_str().unwrap(),
+            &format!("{}:refs/heads/main", theirs),
+        ],
+    )
+    .unwrap();
+
+    run_git(&fixture.work, &["commit", "--amend", "-m", "Reworded"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--force-with-lease", "--check-divergence"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert_eq!(
+        report_status(&report, "origin").as_deref(),
+        Some("failed [stale lease]")
+    );
+    for remote in ["origin", "mirror"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path(remote), "refs/heads/main"),
+            Some(theirs.clone()),
+            "{} should still have their commit",
+            remote
+        );
+    }
+}
+
+#[test]
+fn test_divergence_check_leaves_tracking_refs_alone() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    // A branch someone else created, which fetching origin would track.
+    run_git(&fixture.remote_path("origin"), &["branch", "feature"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--dry-run", "--check-divergence"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert_eq!(
+        rev_parse(&fixture.work, "refs/remotes/origin/feature"),
+        None
+    );
+    let scratch = run_git(&fixture.work, &["for-each-ref", "refs/andiamo/"]).unwrap();
+    assert!(scratch.is_empty(), "scratch refs left behind: {}", scratch);
+}
+
+#[test]
+fn test_diverged_mirror_mode_remote_does_not_block_push() {
+    let fixture = Fixture::new(&["origin", "backup"]);
+    // Committed, so that checking out other commits keeps it around.
+    fixture.commit(
+        ".andiamo.toml",
+        "[push]\ncheck_divergence = true\n\n[[remotes]]\nname = \"origin\"\n\n[[remotes]]\nname = \"backup\"\nmode = \"mirror\"\n",
+        "Configure remotes",
+    );
+    fixture.andiamo(&["push", "--yes"]);
+    let base = fixture.head();
+    fixture.commit_behind_our_back("backup", &base, "Stray commit");
+    fixture.commit("ours.txt", "ours", "Our commit");
+
+    let output = fixture.andiamo(&["push", "--yes"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(!stdout(&output).contains("has diverged"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("backup"), "refs/heads/main"),
+        Some(fixture.head())
+    );
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::{Fixture, andiamo_in, rev_parse, run_git, stderr, stdout};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_init_dry_run_creates_nothing() {
+    let dir = TempDir::new().unwrap();
+
+    let output = andiamo_in(dir.path(), &["init", "--dry-run"], &[]);
+
+    assert!(output.status.success(), "init failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("Would run: git init"));
+    assert!(!dir.path().join(".git").exists());
+}
+
+#[test]
+fn test_remote_add_dry_run_prints_commands() {
+    let fixture = Fixture::new(&["origin"]);
+
+    let output = fixture.andiamo(&[
+        "remote",
+        "add",
+        "--dry-run",
+        "--non-interactive",
+        "--remotes",
+        "origin,codeberg",
+        "--remote",
+        "codeberg=https://codeberg.org/me/repo.git",
+    ]);
+
+    assert!(output.status.success(), "add failed: {}", stderr(&output));
+    let text = stdout(&output);
+    assert!(text.contains("Remote 'origin' already exists"));
+    assert!(text.contains("Would run: git remote add codeberg https://codeberg.org/me/repo.git"));
+    let remotes = run_git(&fixture.work, &["remote"]).unwrap();
+    assert!(!remotes.lines().any(|line| line == "codeberg"));
+}
+
+#[test]
+fn test_push_dry_run_previews_ref_updates() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    let before = fixture.head();
+    let head = fixture.commit("next.txt", "next", "Next commit");
+
+    let output = fixture.andiamo(&["push", "--dry-run"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    let text = stdout(&output);
+    assert!(text.contains("Dry run of push for main"));
+    assert!(
+        text.contains("$ git push --porcelain --dry-run origin refs/heads/main:refs/heads/main")
+    );
+    assert!(text.contains(&format!(
+        "branch main: fast-forward {}..{}",
+        &before[..7],
+        &head[..7]
+    )));
+    assert!(text.contains("nothing was pushed"));
+    for name in ["origin", "mirror"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path(name), "refs/heads/main"),
+            Some(before.clone())
+        );
+    }
+}
+
+#[test]
+fn test_push_dry_run_skips_mirror_confirmation() {
+    let fixture = Fixture::new(&["origin", "backup"]);
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[[remotes]]\nname = \"origin\"\n\n[[remotes]]\nname = \"backup\"\nmode = \"mirror\"\n",
+    )
+    .unwrap();
+    run_git(&fixture.work, &["branch", "old"]).unwrap();
+    assert!(fixture.andiamo(&["push"]).status.success());
+    run_git(&fixture.work, &["branch", "-D", "old"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--dry-run"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("branch old: deleted"));
+    assert!(rev_parse(&fixture.remote_path("backup"), "refs/heads/old").is_some());
+}
+
+#[test]
+fn test_sync_dry_run_with_missing_remote() {
+    let fixture = Fixture::new(&["origin"]);
+    let mirror = fixture.root.path().join("mirror.git");
+
+    let output = fixture.andiamo(&[
+        "sync",
+        "--dry-run",
+        "--non-interactive",
+        "--mirror-url",
+        mirror.to_str().unwrap(),
+    ]);
+
+    assert!(output.status.success(), "sync failed: {}", stderr(&output));
+    let text = stdout(&output);
+    assert!(text.contains(&format!(
+        "Would run: git remote add mirror {}",
+        mirror.display()
+    )));
+    assert!(text.contains("Skipping the push preview until mirror is added."));
+    assert!(rev_parse(&fixture.remote_path("origin"), "refs/heads/main").is_none());
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::{Fixture, rev_parse, run_git, stderr, stdout};
+
+const UNREACHABLE: &str =
+    "echo 'ssh: Could not resolve hostname fake-host: Name or service not known' >&2; exit 255";
+
+#[test]
+fn test_fetch_updates_every_remote() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let start = fixture.head();
+    fixture.andiamo(&["push"]);
+    let theirs = fixture.commit_behind_our_back("mirror", &start, "Their commit");
+
+    let output = fixture.andiamo(&["fetch"]);
+
+    assert!(output.status.success(), "fetch failed: {}", stderr(&output));
+    let report = stdout(&output);
+    assert!(report.contains("origin  up to date"), "{}", report);
+    assert!(report.contains("mirror  success"), "{}", report);
+    assert!(report.contains("main       -> mirror/main"), "{}", report);
+    assert_eq!(
+        rev_parse(&fixture.work, "refs/remotes/mirror/main"),
+        Some(theirs)
+    );
+}
+
+#[test]
+fn test_fetch_prune_removes_deleted_branches() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["branch", "feature"]).unwrap();
+    fixture.andiamo(&["push", "--all"]);
+    let origin = fixture.remote_path("origin");
+    run_git(&origin, &["branch", "-D", "feature"]).unwrap();
+
+    fixture.andiamo(&["fetch"]);
+    assert!(rev_parse(&fixture.work, "refs/remotes/origin/feature").is_some());
+
+    let output = fixture.andiamo(&["fetch", "--prune"]);
+
+    assert!(output.status.success(), "fetch failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("[deleted]"));
+    assert_eq!(
+        rev_parse(&fixture.work, "refs/remotes/origin/feature"),
+        None
+    );
+    assert!(rev_parse(&fixture.work, "refs/remotes/mirror/feature").is_some());
+}
+
+#[test]
+fn test_fetch_reports_unreachable_remote_as_partial_failure() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.route_over_ssh("origin");
+    let ssh = fixture.ssh_wrapper(UNREACHABLE);
+
+    let output = fixture.andiamo_env(&["fetch"], &[("GIT_SSH_COMMAND", &ssh)]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert!(report.contains("origin  failed [network]"), "{}", report);
+    assert!(report.contains("Could not resolve hostname"), "{}", report);
+    assert!(report.contains("mirror  up to date"), "{}", report);
+    assert!(stderr(&output).contains("Partial failure: could not fetch origin"));
+}
+
+#[test]
+fn test_pull_failover_falls_back_to_mirror() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let start = fixture.head();
+    fixture.andiamo(&["push"]);
+    let theirs = fixture.commit_behind_our_back("mirror", &start, "Their commit");
+    fixture.route_over_ssh("origin");
+    let ssh = fixture.ssh_wrapper(UNREACHABLE);
+
+    let output = fixture.andiamo_env(&["pull", "--failover"], &[("GIT_SSH_COMMAND", &ssh)]);
+
+    assert!(output.status.success(), "pull failed: {}", stderr(&output));
+    let printed = stdout(&output);
+    assert!(
+        printed.contains("could not reach origin [network]; pulling from mirror instead"),
+        "{}",
+        printed
+    );
+    assert!(printed.contains("Pulled main from mirror because origin could not be reached"));
+    assert_eq!(fixture.head(), theirs);
+}
+
+#[test]
+fn test_pull_without_failover_fails_when_origin_is_unreachable() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let start = fixture.head();
+    fixture.andiamo(&["push"]);
+    fixture.commit_behind_our_back("mirror", &start, "Their commit");
+    fixture.route_over_ssh("origin");
+    let ssh = fixture.ssh_wrapper(UNREACHABLE);
+
+    let output = fixture.andiamo_env(&["pull"], &[("GIT_SSH_COMMAND", &ssh)]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Could not pull main from origin"));
+    assert!(stderr(&output).contains("Pass --failover"));
+    assert_eq!(fixture.head(), start);
+}
+
+#[test]
+fn test_pull_failover_stops_on_other_failures() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push", "--remotes", "mirror"]);
+
+    // origin is reachable but has no main branch, which the mirror cannot fix.
+    let output = fixture.andiamo(&["pull", "--failover"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!stdout(&output).contains("pulling from mirror"));
+    assert!(stderr(&output).contains("Could not pull main from origin"));
+}
+
+#[test]
+fn test_pull_conflict_shows_git_output() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let start = fixture.head();
+    fixture.andiamo(&["push"]);
+    fixture.commit_behind_our_back("origin", &start, "Their commit");
+    fixture.commit("theirs.txt", "ours", "Our commit");
+    run_git(&fixture.work, &["config", "pull.rebase", "false"]).unwrap();
+
+    let output = fixture.andiamo(&["pull", "--failover"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let printed = stdout(&output);
+    assert!(printed.contains("CONFLICT (add/add)"), "{}", printed);
+    assert!(printed.contains("Automatic merge failed"), "{}", printed);
+    assert!(!printed.contains("pulling from mirror"), "{}", printed);
+    assert!(stderr(&output).contains("Could not pull main from origin"));
+}
@@ -1,10 +1,8 @@
-use andiamo::git_helpers::{
-    GitError, add_remotes_with_urls, check_git_installed, get_existing_remotes, init_repo,
-    push_to_remotes,
-};
+use andiamo::git_helpers::{GitError, check_git_installed};
 use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
 use tempfile::TempDir;
 
 /// Helper function to run git commands in a specific directory
@@ -304,3 +302,37 @@ fn test_git_error_remote_not_found() {
         "Debug string should contain remote name"
     );
 }
+
+#[test]
+fn test_git_error_timeout() {
+    let error = GitError::Timeout {
+        remote: "mirror".to_string(),
+        after: Duration::from_millis(2500),
+    };
+    assert_eq!(error.to_string(), "Remote 'mirror' timed out after 2.5s");
+}
+
+#[test]
+fn test_git_error_verification_failed() {
+    let error = GitError::VerificationFailed {
+        remote: "mirror".to_string(),
+        reference: "refs/heads/main".to_string(),
+        expected: "abc".to_string(),
+        actual: Some("def".to_string()),
+    };
+    assert_eq!(
+        error.to_string(),
+        "Verification failed on 'mirror': refs/heads/main is at def, expected abc"
+    );
+
+    let error = GitError::VerificationFailed {
+        remote: "mirror".to_string(),
+        reference: "refs/tags/v1".to_string(),
+        expected: "abc".to_string(),
+        actual: None,
+    };
+    assert_eq!(
+        error.to_string(),
+        "Verification failed on 'mirror': refs/tags/v1 is missing, expected abc"
+    );
+}
@@ -0,0 +1,74 @@
+use andiamo::porcelain::{RefUpdate, RefUpdateKind, parse_porcelain};
+
+const OUTPUT: &str = "To ../remote.git
+ \trefs/heads/main:refs/heads/main\tbf7e3c3..acbe6fd
++\trefs/heads/topic:refs/heads/topic\t1111111...2222222 (forced update)
+*\trefs/tags/v2:refs/tags/v2\t[new tag]
+-\t:refs/heads/old\t[deleted]
+=\trefs/heads/stable:refs/heads/stable\t[up to date]
+!\trefs/heads/dev:refs/heads/dev\t[rejected] (non-fast-forward)
+!\trefs/heads/prod:refs/heads/prod\t[remote rejected] (pre-receive hook declined)
+Done
+";
+
+#[test]
+fn test_parse_porcelain_kinds() {
+    let kinds: Vec<RefUpdateKind> = parse_porcelain(OUTPUT)
+        .iter()
+        .map(|update| update.kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            RefUpdateKind::FastForward,
+            RefUpdateKind::Forced,
+            RefUpdateKind::New,
+            RefUpdateKind::Deleted,
+            RefUpdateKind::UpToDate,
+            RefUpdateKind::Rejected,
+            RefUpdateKind::RemoteRejected,
+        ]
+    );
+}
+
+#[test]
+fn test_parse_porcelain_shas_and_reasons() {
+    let updates = parse_porcelain(OUTPUT);
+
+    assert_eq!(updates[0].old_sha.as_deref(), Some("bf7e3c3"));
+    assert_eq!(updates[0].new_sha.as_deref(), Some("acbe6fd"));
+    assert_eq!(updates[1].old_sha.as_deref(), Some("1111111"));
+    assert_eq!(updates[1].new_sha.as_deref(), Some("2222222"));
+    assert_eq!(updates[1].reason, None);
+    assert_eq!(updates[3].source, "");
+    assert_eq!(updates[3].destination, "refs/heads/old");
+    assert_eq!(updates[5].reason.as_deref(), Some("non-fast-forward"));
+    assert_eq!(
+        updates[6].reason.as_deref(),
+        Some("pre-receive hook declined")
+    );
+}
+
+#[test]
+fn test_ref_update_display() {
+    let updates = parse_porcelain(OUTPUT);
+
+    assert_eq!(
+        updates[0].to_string(),
+        "branch main: fast-forward bf7e3c3..acbe6fd"
+    );
+    assert_eq!(updates[2].to_string(), "tag v2: created");
+    assert_eq!(updates[3].to_string(), "branch old: deleted");
+    assert_eq!(
+        updates[6].to_string(),
+        "branch prod: remote rejected (pre-receive hook declined)"
+    );
+}
+
+#[test]
+fn test_parse_ignores_other_lines() {
+    assert_eq!(RefUpdate::parse("To ../remote.git"), None);
+    assert_eq!(RefUpdate::parse("Done"), None);
+    assert!(parse_porcelain("Everything up-to-date\n").is_empty());
+}
@@ -0,0 +1,1005 @@
+mod common;
+
+use andiamo::git_helpers::GitError;
+use andiamo::porcelain::parse_porcelain;
+use andiamo::push::{
+    PushReport, PushStatus, PushSummary, RefMismatch, RemotePushResult, SuccessPolicy,
+    UpstreamTarget,
+};
+use andiamo::remotes::RemoteRole;
+use andiamo::retry::FailureKind;
+use std::fs;
+use std::time::Duration;
+
+use common::{Fixture, report_status, rev_parse, run_git, stderr, stdout};
+
+#[test]
+fn test_push_to_default_remotes() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let head = fixture.head();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    for name in ["origin", "mirror"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path(name), "refs/heads/main"),
+            Some(head.clone()),
+            "{} should have the pushed commit",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_push_to_any_number_of_remotes() {
+    let fixture = Fixture::new(&["origin", "github-mirror", "codeberg", "nas-backup"]);
+    let head = fixture.head();
+
+    let output = fixture.andiamo(&[
+        "push",
+        "--remotes",
+        "origin,github-mirror,codeberg,nas-backup",
+    ]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    for name in ["origin", "github-mirror", "codeberg", "nas-backup"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path(name), "refs/heads/main"),
+            Some(head.clone()),
+            "{} should have the pushed commit",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_push_missing_remote_fails() {
+    let fixture = Fixture::new(&["origin"]);
+
+    let output = fixture.andiamo(&["push", "--remotes", "origin,codeberg"]);
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("codeberg"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        None
+    );
+}
+
+#[test]
+fn test_legacy_push_flag_still_works() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+
+    let output = fixture.andiamo(&["--push"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stderr(&output).contains("deprecated"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(fixture.head())
+    );
+}
+
+/// A pre-receive hook that records its start in `marks` and then waits for
+/// `other` to start too, failing if that never happens.
+fn rendezvous_hook(marks: &std::path::Path, me: &str, other: &str) -> String {
+    format!(
+        "#!/bin/sh\ntouch '{dir}/{me}'\ni=0\nwhile [ ! -f '{dir}/{other}' ]; do\n  sleep 0.1\n  i=$((i+1))\n  [ $i -gt 30 ] && exit 1\ndone\nexit 0\n",
+        dir = marks.display(),
+        me = me,
+        other = other
+    )
+}
+
+#[test]
+fn test_push_runs_remotes_concurrently() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let marks = fixture.root.path().join("marks");
+    std::fs::create_dir(&marks).unwrap();
+    fixture.install_hook(
+        "origin",
+        "pre-receive",
+        &rendezvous_hook(&marks, "origin", "mirror"),
+    );
+    fixture.install_hook(
+        "mirror",
+        "pre-receive",
+        &rendezvous_hook(&marks, "mirror", "origin"),
+    );
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+}
+
+#[test]
+fn test_push_jobs_limit() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let marks = fixture.root.path().join("marks");
+    std::fs::create_dir(&marks).unwrap();
+    fixture.install_hook(
+        "origin",
+        "pre-receive",
+        &rendezvous_hook(&marks, "origin", "mirror"),
+    );
+
+    let output = fixture.andiamo(&["push", "--jobs", "1", "--keep-going"]);
+
+    assert!(
+        !output.status.success(),
+        "origin's hook can only succeed if mirror is pushed at the same time"
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(fixture.head()),
+        "mirror is still attempted after origin fails"
+    );
+}
+
+#[test]
+fn test_push_report_is_in_remote_order() {
+    let fixture = Fixture::new(&["origin", "mirror", "codeberg"]);
+    fixture.install_hook("origin", "pre-receive", "#!/bin/sh\nsleep 1\nexit 0\n");
+
+    let output = fixture.andiamo(&["push", "--remotes", "origin,mirror,codeberg"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    let report = stdout(&output);
+    let origin = report.find("origin ").unwrap();
+    let mirror = report.find("mirror ").unwrap();
+    let codeberg = report.find("codeberg ").unwrap();
+    assert!(
+        origin < mirror && mirror < codeberg,
+        "report was:\n{}",
+        report
+    );
+}
+
+const REJECT_HOOK: &str = "#!/bin/sh\necho 'rejected by policy' >&2\nexit 1\n";
+
+fn result(remote: &str, status: PushStatus) -> RemotePushResult {
+    RemotePushResult {
+        remote: remote.to_string(),
+        role: RemoteRole::default_for(remote),
+        status,
+        failure: None,
+        attempts: 1,
+        elapsed: Duration::ZERO,
+        refs: Vec::new(),
+        command: Vec::new(),
+        mismatches: Vec::new(),
+        stdout: String::new(),
+        stderr: String::new(),
+    }
+}
+
+#[test]
+fn test_push_report_summary() {
+    let mut report = PushReport {
+        target: "main".to_string(),
+        dry_run: false,
+        policy: SuccessPolicy::All,
+        results: vec![
+            result("origin", PushStatus::Success),
+            result("mirror", PushStatus::UpToDate),
+            result("nas", PushStatus::Skipped("not configured".to_string())),
+        ],
+    };
+    assert_eq!(report.summary(), PushSummary::AllSucceeded);
+    assert!(report.check().is_ok());
+
+    report.results[1].status = PushStatus::Failed;
+    assert_eq!(report.summary(), PushSummary::PartialFailure);
+    assert_eq!(report.failed_remotes(), vec!["mirror"]);
+
+    report.results[0].status = PushStatus::Failed;
+    assert_eq!(report.summary(), PushSummary::AllFailed);
+
+    assert_eq!(PushSummary::AllSucceeded.exit_code(), 0);
+    assert_eq!(PushSummary::PartialFailure.exit_code(), 3);
+    assert_eq!(PushSummary::AllFailed.exit_code(), 1);
+    assert_eq!(PushSummary::NothingPushed.exit_code(), 2);
+}
+
+#[test]
+fn test_push_report_with_every_remote_skipped() {
+    let report = PushReport {
+        target: "main".to_string(),
+        dry_run: false,
+        policy: SuccessPolicy::All,
+        results: vec![
+            result("origin", PushStatus::Skipped("not configured".to_string())),
+            result("mirror", PushStatus::Skipped("not configured".to_string())),
+        ],
+    };
+
+    assert_eq!(report.summary(), PushSummary::NothingPushed);
+    assert!(matches!(report.check(), Err(GitError::Usage(_))));
+}
+
+#[test]
+fn test_push_skipped_by_every_remote_is_not_success() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[[remotes]]\nname = \"origin\"\nbranches = [\"release/*\"]\n\n[[remotes]]\nname = \"mirror\"\nbranches = [\"release/*\"]\n",
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let report = stdout(&output);
+    assert!(
+        report.contains("Every remote was skipped; nothing was pushed."),
+        "{}",
+        report
+    );
+    assert!(!report.contains("pushed successfully"), "{}", report);
+    assert!(stderr(&output).contains("Every remote skipped main"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        None
+    );
+}
+
+#[test]
+fn test_rejected_refs_and_tags() {
+    let mut origin = result("origin", PushStatus::Failed);
+    origin.refs = parse_porcelain(
+        "*\trefs/tags/v2:refs/tags/v2\t[new tag]
+!\trefs/tags/v1:refs/tags/v1\t[rejected] (already exists)
+!\trefs/heads/main:refs/heads/main\t[remote rejected] (pre-receive hook declined)
+",
+    );
+
+    let rejected: Vec<&str> = origin
+        .rejected_refs()
+        .iter()
+        .map(|update| update.destination.as_str())
+        .collect();
+    assert_eq!(rejected, vec!["refs/tags/v1", "refs/heads/main"]);
+    assert_eq!(origin.rejected_tags(), vec!["v1"]);
+}
+
+#[test]
+fn test_push_partial_failure_reports_every_remote() {
+    let fixture = Fixture::new(&["origin", "mirror", "codeberg"]);
+    fixture.install_hook("origin", "pre-receive", REJECT_HOOK);
+
+    let output = fixture.andiamo(&[
+        "push",
+        "--remotes",
+        "origin,mirror,codeberg",
+        "--keep-going",
+    ]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert!(
+        report_status(&report, "origin").as_deref() == Some("failed [rejected]"),
+        "report was:\n{}",
+        report
+    );
+    assert!(report.contains("rejected by policy"));
+    assert_eq!(report_status(&report, "mirror").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "codeberg").as_deref(),
+        Some("success")
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("codeberg"), "refs/heads/main"),
+        Some(fixture.head())
+    );
+}
+
+#[test]
+fn test_push_all_failed_exit_code() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.install_hook("origin", "pre-receive", REJECT_HOOK);
+    fixture.install_hook("mirror", "pre-receive", REJECT_HOOK);
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("could not push to origin, mirror"));
+}
+
+#[test]
+fn test_push_stops_after_failure_without_keep_going() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.install_hook("origin", "pre-receive", REJECT_HOOK);
+
+    let output = fixture.andiamo(&["push", "--jobs", "1"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        report_status(&stdout(&output), "mirror").as_deref(),
+        Some("skipped (not attempted after an earlier failure)")
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        None
+    );
+}
+
+#[test]
+fn test_push_up_to_date() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    assert!(fixture.andiamo(&["push"]).status.success());
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        report_status(&stdout(&output), "origin").as_deref(),
+        Some("up-to-date")
+    );
+}
+
+#[test]
+fn test_success_policy_parsing() {
+    assert_eq!("all".parse(), Ok(SuccessPolicy::All));
+    assert_eq!("primary".parse(), Ok(SuccessPolicy::Primary));
+    assert_eq!("quorum=2".parse(), Ok(SuccessPolicy::Quorum(2)));
+    assert!("quorum=0".parse::<SuccessPolicy>().is_err());
+    assert!("most".parse::<SuccessPolicy>().is_err());
+    assert_eq!(SuccessPolicy::Quorum(3).to_string(), "quorum=3");
+}
+
+#[test]
+fn test_upstream_target_parsing() {
+    assert_eq!("primary".parse(), Ok(UpstreamTarget::Primary));
+    assert_eq!("none".parse(), Ok(UpstreamTarget::Off));
+    assert_eq!(
+        "codeberg".parse(),
+        Ok(UpstreamTarget::Remote("codeberg".to_string()))
+    );
+    assert!("".parse::<UpstreamTarget>().is_err());
+}
+
+#[test]
+fn test_policy_evaluation() {
+    let mut report = PushReport {
+        target: "main".to_string(),
+        dry_run: false,
+        policy: SuccessPolicy::Primary,
+        results: vec![
+            result("origin", PushStatus::Success),
+            result("mirror", PushStatus::Failed),
+            result("codeberg", PushStatus::Success),
+        ],
+    };
+    assert_eq!(report.summary(), PushSummary::PolicySatisfied);
+    assert!(report.check().is_ok());
+
+    report.policy = SuccessPolicy::All;
+    assert_eq!(report.summary(), PushSummary::PartialFailure);
+
+    report.policy = SuccessPolicy::Quorum(2);
+    assert_eq!(report.summary(), PushSummary::PolicySatisfied);
+    report.policy = SuccessPolicy::Quorum(3);
+    assert_eq!(report.summary(), PushSummary::PartialFailure);
+
+    report.policy = SuccessPolicy::Primary;
+    report.results[0].status = PushStatus::Failed;
+    assert_eq!(report.summary(), PushSummary::PartialFailure);
+}
+
+#[test]
+fn test_primary_policy_tolerates_mirror_failure() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.install_hook("mirror", "pre-receive", REJECT_HOOK);
+
+    let output = fixture.andiamo(&["push", "--policy", "primary"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    let report = stdout(&output);
+    assert_eq!(report_status(&report, "origin").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "mirror").as_deref(),
+        Some("failed [rejected]")
+    );
+}
+
+#[test]
+fn test_primary_policy_fails_when_primary_fails() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.install_hook("origin", "pre-receive", REJECT_HOOK);
+
+    let output = fixture.andiamo(&["push", "--policy", "primary", "--keep-going"]);
+
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_roles_and_policy_from_config() {
+    let fixture = Fixture::new(&["gitlab", "mirror"]);
+    fixture.install_hook("mirror", "pre-receive", REJECT_HOOK);
+    std::fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[push]\npolicy = \"primary\"\n\n[[remotes]]\nname = \"gitlab\"\nrole = \"primary\"\n\n[[remotes]]\nname = \"mirror\"\n",
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("gitlab  primary"));
+}
+
+#[test]
+fn test_quorum_larger_than_remote_count_is_usage_error() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+
+    let output = fixture.andiamo(&["push", "--policy", "quorum=3"]);
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_push_all_tags() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["tag", "v1.0"]).unwrap();
+    run_git(&fixture.work, &["tag", "-a", "v1.1", "-m", "Release 1.1"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--tags"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    let report = stdout(&output);
+    assert!(report.contains("Push report for main and all tags"));
+    assert_eq!(report.matches("tag v1.0: created").count(), 2);
+    assert_eq!(report.matches("tag v1.1: created").count(), 2);
+    for name in ["origin", "mirror"] {
+        assert!(rev_parse(&fixture.remote_path(name), "refs/tags/v1.0").is_some());
+        assert!(rev_parse(&fixture.remote_path(name), "refs/heads/main").is_some());
+    }
+}
+
+#[test]
+fn test_push_follow_tags_skips_lightweight_tags() {
+    let fixture = Fixture::new(&["origin"]);
+    run_git(&fixture.work, &["tag", "light"]).unwrap();
+    run_git(&fixture.work, &["tag", "-a", "v2.0", "-m", "Release 2.0"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--follow-tags", "--remotes", "origin"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("tag v2.0: created"));
+    assert!(rev_parse(&fixture.remote_path("origin"), "refs/tags/v2.0").is_some());
+    assert!(rev_parse(&fixture.remote_path("origin"), "refs/tags/light").is_none());
+}
+
+#[test]
+fn test_push_single_tag_reports_rejection_per_remote() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["tag", "v1.0"]).unwrap();
+    fixture.andiamo(&["push", "--tag", "v1.0", "--remotes", "mirror"]);
+
+    // Move the tag locally so the mirror's copy no longer matches.
+    fixture.commit("later.txt", "later", "Later commit");
+    run_git(&fixture.work, &["tag", "-f", "v1.0"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--tag", "v1.0", "--keep-going"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert!(report.contains("Push report for tag v1.0"));
+    assert_eq!(report_status(&report, "origin").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "mirror").as_deref(),
+        Some("failed [rejected]")
+    );
+    assert!(report.contains("tag v1.0: created"));
+    assert!(report.contains("tag v1.0: rejected (already exists)"));
+    assert!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main").is_none(),
+        "--tag should not push the branch"
+    );
+}
+
+#[test]
+fn test_push_missing_tag_is_usage_error() {
+    let fixture = Fixture::new(&["origin"]);
+
+    let output = fixture.andiamo(&["push", "--tag", "nope", "--remotes", "origin"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Tag 'nope' does not exist"));
+}
+
+fn create_branches(fixture: &Fixture, names: &[&str]) {
+    for name in names {
+        run_git(&fixture.work, &["branch", name]).unwrap();
+    }
+}
+
+#[test]
+fn test_push_branches_matching_glob() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    create_branches(&fixture, &["release/1.0", "release/2.0", "feature/x"]);
+
+    let output = fixture.andiamo(&["push", "--branch", "release/*"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("Push report for release/1.0, release/2.0"));
+    for name in ["origin", "mirror"] {
+        let remote = fixture.remote_path(name);
+        assert!(rev_parse(&remote, "refs/heads/release/1.0").is_some());
+        assert!(rev_parse(&remote, "refs/heads/release/2.0").is_some());
+        assert!(rev_parse(&remote, "refs/heads/feature/x").is_none());
+        assert!(rev_parse(&remote, "refs/heads/main").is_none());
+    }
+}
+
+#[test]
+fn test_push_all_branches_uses_one_invocation_per_remote() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    create_branches(&fixture, &["develop", "release/1.0"]);
+    fixture.route_over_ssh("origin");
+    let ssh = fixture.ssh_wrapper("");
+
+    let output = fixture.andiamo_env(
+        &["push", "--all", "--skip-verify", "--no-check-divergence"],
+        &[("GIT_SSH_COMMAND", &ssh)],
+    );
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert_eq!(fixture.ssh_invocations(), 1);
+    for name in ["origin", "mirror"] {
+        for branch in ["main", "develop", "release/1.0"] {
+            assert!(
+                rev_parse(
+                    &fixture.remote_path(name),
+                    &format!("refs/heads/{}", branch)
+                )
+                .is_some(),
+                "{} should have {}",
+                name,
+                branch
+            );
+        }
+    }
+}
+
+#[test]
+fn test_push_branch_glob_without_matches_is_usage_error() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+
+    let output = fixture.andiamo(&["push", "--branch", "release/*"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("No local branches match 'release/*'"));
+}
+
+const MIRROR_CONFIG: &str =
+    "[[remotes]]\nname = \"origin\"\n\n[[remotes]]\nname = \"backup\"\nmode = \"mirror\"\n";
+
+#[test]
+fn test_mirror_mode_replicates_every_ref() {
+    let fixture = Fixture::new(&["origin", "backup"]);
+    fs::write(fixture.work.join(".andiamo.toml"), MIRROR_CONFIG).unwrap();
+    create_branches(&fixture, &["develop"]);
+    run_git(&fixture.work, &["tag", "v1.0"]).unwrap();
+    run_git(&fixture.work, &["notes", "add", "-m", "reviewed"]).unwrap();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    let backup = fixture.remote_path("backup");
+    assert!(rev_parse(&backup, "refs/heads/main").is_some());
+    assert!(rev_parse(&backup, "refs/heads/develop").is_some());
+    assert!(rev_parse(&backup, "refs/tags/v1.0").is_some());
+    assert!(rev_parse(&backup, "refs/notes/commits").is_some());
+
+    let origin = fixture.remote_path("origin");
+    assert!(rev_parse(&origin, "refs/heads/main").is_some());
+    assert!(rev_parse(&origin, "refs/heads/develop").is_none());
+}
+
+#[test]
+fn test_mirror_deletions_need_confirmation() {
+    let fixture = Fixture::new(&["origin", "backup"]);
+    fs::write(fixture.work.join(".andiamo.toml"), MIRROR_CONFIG).unwrap();
+    create_branches(&fixture, &["old"]);
+    assert!(fixture.andiamo(&["push"]).status.success());
+    run_git(&fixture.work, &["branch", "-D", "old"]).unwrap();
+
+    let refused = fixture.andiamo(&["push"]);
+
+    assert_eq!(refused.status.code(), Some(2));
+    assert!(stdout(&refused).contains("backup: refs/heads/old"));
+    assert!(stderr(&refused).contains("--yes"));
+    assert!(rev_parse(&fixture.remote_path("backup"), "refs/heads/old").is_some());
+
+    let confirmed = fixture.andiamo(&["push", "--yes"]);
+
+    assert!(
+        confirmed.status.success(),
+        "push failed: {}",
+        stderr(&confirmed)
+    );
+    assert!(stdout(&confirmed).contains("branch old: deleted"));
+    assert!(rev_parse(&fixture.remote_path("backup"), "refs/heads/old").is_none());
+}
+
+const UNREACHABLE: &str =
+    "echo 'ssh: Could not resolve hostname fake-host: Name or service not known' >&2; exit 255";
+
+#[test]
+fn test_unreachable_mirror_does_not_block_other_remotes() {
+    let fixture = Fixture::new(&["origin", "backup"]);
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        format!(
+            "[push]\npolicy = \"primary\"\nkeep_going = true\n\n{}",
+            MIRROR_CONFIG
+        ),
+    )
+    .unwrap();
+    fixture.route_over_ssh("backup");
+    let ssh = fixture.ssh_wrapper(UNREACHABLE);
+
+    let output = fixture.andiamo_env(&["push", "--retries", "0"], &[("GIT_SSH_COMMAND", &ssh)]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    let report = stdout(&output);
+    assert_eq!(report_status(&report, "origin").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "backup").as_deref(),
+        Some("failed [network]")
+    );
+    assert!(report.contains("Could not list the refs a push would delete"));
+    assert_eq!(
+        fixture.ssh_invocations(),
+        1,
+        "backup should not be pushed to again"
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        Some(fixture.head())
+    );
+}
+
+#[test]
+fn test_mirror_check_is_skipped_with_yes() {
+    let fixture = Fixture::new(&["origin", "backup"]);
+    fs::write(fixture.work.join(".andiamo.toml"), MIRROR_CONFIG).unwrap();
+    fixture.route_over_ssh("backup");
+    let ssh = fixture.ssh_wrapper("");
+
+    let output = fixture.andiamo_env(
+        &["push", "--yes", "--skip-verify"],
+        &[("GIT_SSH_COMMAND", &ssh)],
+    );
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert_eq!(fixture.ssh_invocations(), 1);
+}
+
+#[test]
+fn test_detached_head_fails_before_checking_mirrors() {
+    let fixture = Fixture::new(&["origin", "backup"]);
+    fs::write(fixture.work.join(".andiamo.toml"), MIRROR_CONFIG).unwrap();
+    fixture.route_over_ssh("backup");
+    let ssh = fixture.ssh_wrapper("");
+    run_git(&fixture.work, &["checkout", "--detach"]).unwrap();
+
+    let output = fixture.andiamo_env(&["push"], &[("GIT_SSH_COMMAND", &ssh)]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("HEAD is detached"));
+    assert_eq!(fixture.ssh_invocations(), 0);
+}
+
+#[test]
+fn test_push_detached_head_fails_up_front() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["checkout", "--detach"]).unwrap();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("HEAD is detached"));
+    assert!(!stdout(&output).contains("Push report"));
+    assert!(rev_parse(&fixture.remote_path("origin"), "refs/heads/HEAD").is_none());
+}
+
+#[test]
+fn test_push_detached_head_to_ref() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["checkout", "--detach"]).unwrap();
+    let head = fixture.commit("fix.txt", "fix", "Detached fix");
+
+    let output = fixture.andiamo(&["push", "--to-ref", "refs/heads/hotfix"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("Push report for HEAD to hotfix"));
+    for name in ["origin", "mirror"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path(name), "refs/heads/hotfix"),
+            Some(head.clone())
+        );
+        assert!(rev_parse(&fixture.remote_path(name), "refs/heads/main").is_none());
+    }
+}
+
+#[test]
+fn test_force_with_lease_updates_rewritten_branch() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    run_git(&fixture.work, &["commit", "--amend", "-m", "Reworded"]).unwrap();
+    let rewritten = fixture.head();
+
+    let rejected = fixture.andiamo(&["push", "--keep-going"]);
+    assert_eq!(rejected.status.code(), Some(1));
+
+    let output = fixture.andiamo(&["push", "--force-with-lease"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("branch main: forced"));
+    for name in ["origin", "mirror"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path(name), "refs/heads/main"),
+            Some(rewritten.clone())
+        );
+    }
+}
+
+#[test]
+fn test_force_with_lease_checks_each_remote_separately() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+
+    // Someone else updates the mirror; pushing by path leaves this clone's
+    // refs/remotes/mirror/main where it was.
+    run_git(&fixture.work, &["checkout", "-q", "-b", "other"]).unwrap();
+    let theirs = fixture.commit("theirs.txt", "theirs", "Their commit");
+    let mirror_path = fixture.remote_path("mirror");
+    run_git(
+        &fixture.work,
+        &["push", mirror_path.to_str().unwrap(), "other:main"],
+    )
+    .unwrap();
+    run_git(&fixture.work, &["checkout", "-q", "main"]).unwrap();
+
+    run_git(&fixture.work, &["commit", "--amend", "-m", "Reworded"]).unwrap();
+    let rewritten = fixture.head();
+
+    let output = fixture.andiamo(&["push", "--force-with-lease", "--keep-going"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert_eq!(report_status(&report, "origin").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "mirror").as_deref(),
+        Some("failed [stale lease]")
+    );
+    assert!(report.contains("branch main: rejected (stale info)"));
+    assert!(report.contains("mirror changed since it was last fetched"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        Some(rewritten)
+    );
+    assert_eq!(rev_parse(&mirror_path, "refs/heads/main"), Some(theirs));
+}
+
+#[test]
+fn test_atomic_push_keeps_branch_and_tag_together() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let before = fixture.head();
+    run_git(&fixture.work, &["tag", "v1.0"]).unwrap();
+    fixture.andiamo(&["push", "--tags", "--remotes", "mirror"]);
+
+    // The mirror's v1.0 now differs from the local one, so its tag update
+    // is rejected and the branch must not move either.
+    let after = fixture.commit("later.txt", "later", "Later commit");
+    run_git(&fixture.work, &["tag", "-f", "v1.0"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--tags", "--atomic", "--keep-going"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert_eq!(report_status(&report, "origin").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "mirror").as_deref(),
+        Some("failed [rejected]")
+    );
+    assert!(report.contains("branch main: rejected (atomic push failed)"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        Some(after)
+    );
+    for name in ["refs/heads/main", "refs/tags/v1.0"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path("mirror"), name),
+            Some(before.clone()),
+            "{} should be unchanged on the mirror",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_atomic_push_reports_remote_without_support() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(
+        &fixture.remote_path("mirror"),
+        &["config", "receive.advertiseAtomic", "false"],
+    )
+    .unwrap();
+    std::fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[push]\natomic = true\n",
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["push", "--keep-going"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert_eq!(report_status(&report, "origin").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "mirror").as_deref(),
+        Some("failed [no atomic support]")
+    );
+    assert!(report.contains("mirror does not advertise atomic pushes"));
+    assert!(rev_parse(&fixture.remote_path("mirror"), "refs/heads/main").is_none());
+}
+
+fn upstream(fixture: &Fixture, branch: &str) -> Option<String> {
+    run_git(
+        &fixture.work,
+        &["config", "--get", &format!("branch.{}.remote", branch)],
+    )
+    .ok()
+    .map(|remote| remote.trim().to_string())
+}
+
+#[test]
+fn test_push_sets_upstream_on_primary_only() {
+    let fixture = Fixture::new(&["mirror", "origin"]);
+    run_git(&fixture.work, &["checkout", "-q", "-b", "feature"]).unwrap();
+
+    let output = fixture.andiamo(&["push", "--remotes", "mirror,origin"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("Branch 'feature' set up to track 'origin/feature'."));
+    assert_eq!(upstream(&fixture, "feature").as_deref(), Some("origin"));
+    assert_eq!(
+        run_git(&fixture.work, &["config", "--get", "branch.feature.merge"])
+            .unwrap()
+            .trim(),
+        "refs/heads/feature"
+    );
+}
+
+#[test]
+fn test_push_keeps_existing_upstream() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["config", "branch.main.remote", "mirror"]).unwrap();
+    run_git(
+        &fixture.work,
+        &["config", "branch.main.merge", "refs/heads/main"],
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(!stdout(&output).contains("set up to track"));
+    assert_eq!(upstream(&fixture, "main").as_deref(), Some("mirror"));
+}
+
+#[test]
+fn test_push_upstream_can_be_disabled_or_redirected() {
+    let fixture = Fixture::new(&["origin", "codeberg"]);
+    let remotes = ["--remotes", "origin,codeberg"];
+
+    let output = fixture.andiamo(&[&["push", "--set-upstream", "none"][..], &remotes].concat());
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert_eq!(upstream(&fixture, "main"), None);
+
+    run_git(&fixture.work, &["checkout", "-q", "-b", "feature"]).unwrap();
+    let output = fixture.andiamo(&[&["push", "--set-upstream", "codeberg"][..], &remotes].concat());
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert_eq!(upstream(&fixture, "feature").as_deref(), Some("codeberg"));
+}
+
+#[test]
+fn test_mirror_mode_remote_never_becomes_upstream() {
+    let fixture = Fixture::new(&["backup"]);
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[push]\nupstream = \"backup\"\n\n[[remotes]]\nname = \"backup\"\nmode = \"mirror\"\n",
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["push", "--yes"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert!(stdout(&output).contains(
+        "Warning: not setting upstreams to backup: mirror remotes never become an upstream."
+    ));
+    assert_eq!(upstream(&fixture, "main"), None);
+}
+
+#[test]
+fn test_unknown_upstream_remote_is_usage_error() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+
+    let output = fixture.andiamo(&["push", "--set-upstream", "nope"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Cannot set upstreams to 'nope'"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        None,
+        "nothing should be pushed"
+    );
+}
+
+/// Quietly moves `main` back one commit after every push.
+const REWIND_HOOK: &str =
+    "#!/bin/sh\ngit update-ref refs/heads/main refs/heads/main~1 2>/dev/null || true\n";
+
+#[test]
+fn test_push_verification_catches_rewritten_ref() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    let parent = fixture.head();
+    fixture.install_hook("mirror", "post-receive", REWIND_HOOK);
+    let head = fixture.commit("later.txt", "later", "Later commit");
+
+    let output = fixture.andiamo(&["push"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert_eq!(report_status(&report, "origin").as_deref(), Some("success"));
+    assert_eq!(
+        report_status(&report, "mirror").as_deref(),
+        Some("failed [verification]")
+    );
+    assert!(report.contains(&format!(
+        "Verification failed on 'mirror': refs/heads/main is at {}, expected {}",
+        parent, head
+    )));
+}
+
+#[test]
+fn test_push_verification_can_be_skipped() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    fixture.install_hook("mirror", "post-receive", REWIND_HOOK);
+    fixture.commit("later.txt", "later", "Later commit");
+
+    let output = fixture.andiamo(&["push", "--skip-verify"]);
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+}
+
+#[test]
+fn test_verification_error_names_remote_and_shas() {
+    let mut failed = result("mirror", PushStatus::Failed);
+    failed.failure = Some(FailureKind::Verification);
+    failed.mismatches = vec![RefMismatch {
+        reference: "refs/heads/main".to_string(),
+        expected: "a".repeat(40),
+        actual: None,
+    }];
+
+    match failed.error() {
+        Some(GitError::VerificationFailed {
+            remote,
+            expected,
+            actual,
+            ..
+        }) => {
+            assert_eq!(remote, "mirror");
+            assert_eq!(expected, "a".repeat(40));
+            assert_eq!(actual, None);
+        }
+        other => panic!("expected a verification error, got {:?}", other),
+    }
+}
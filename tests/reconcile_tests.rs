@@ -0,0 +1,191 @@
+mod common;
+
+use common::{Fixture, rev_parse, run_git, stderr, stdout};
+
+/// Splits `main` between origin and the mirror: origin gets "Our commit"
+/// and the mirror "Their commit", both on top of the returned base.
+fn diverge(fixture: &Fixture) -> (String, String, String) {
+    fixture.andiamo(&["push"]);
+    let base = fixture.head();
+    let theirs = fixture.commit_behind_our_back("mirror", &base, "Their commit");
+    let ours = fixture.commit("ours.txt", "ours", "Our commit");
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+    (base, ours, theirs)
+}
+
+#[test]
+fn test_reconcile_overwrite_preview() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let (_, _, theirs) = diverge(&fixture);
+
+    let output = fixture.andiamo(&["reconcile", "--strategy", "overwrite", "--dry-run"]);
+
+    assert!(
+        output.status.success(),
+        "reconcile failed: {}",
+        stderr(&output)
+    );
+    let preview = stdout(&output);
+    assert!(preview.contains("Branch main has diverged between origin and mirror"));
+    assert!(preview.contains(&format!(
+        "Would run: git push --porcelain --force-with-lease=refs/heads/main:{} mirror refs/remotes/origin/main:refs/heads/main",
+        theirs
+    )));
+    assert!(preview.contains("dropping 1 commit(s) only mirror has"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(theirs)
+    );
+}
+
+#[test]
+fn test_reconcile_overwrite_needs_confirmation() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let (_, ours, theirs) = diverge(&fixture);
+
+    let refused = fixture.andiamo(&["reconcile", "--strategy", "overwrite"]);
+    assert_eq!(refused.status.code(), Some(2));
+    assert!(stderr(&refused).contains("Pass --yes"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(theirs)
+    );
+
+    let output = fixture.andiamo(&["reconcile", "--strategy", "overwrite", "--yes"]);
+
+    assert!(
+        output.status.success(),
+        "reconcile failed: {}",
+        stderr(&output)
+    );
+    assert!(stdout(&output).contains("Overwrote main on mirror with origin's history"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(ours)
+    );
+}
+
+#[test]
+fn test_reconcile_merge_pushes_both_sides_everywhere() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let (_, ours, theirs) = diverge(&fixture);
+    std::fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[push]\ncheck_divergence = true\n",
+    )
+    .unwrap();
+
+    let preview = fixture.andiamo(&["reconcile", "--strategy", "merge", "--dry-run"]);
+    assert!(stdout(&preview).contains("Would run: git merge --no-edit refs/remotes/mirror/main"));
+    assert!(stdout(&preview).contains("Would then push main to origin, mirror."));
+    assert_eq!(fixture.head(), ours);
+
+    let output = fixture.andiamo(&["reconcile", "--strategy", "merge"]);
+
+    assert!(
+        output.status.success(),
+        "reconcile failed: {}",
+        stderr(&output)
+    );
+    let merged = fixture.head();
+    for commit in [&ours, &theirs] {
+        assert!(
+            run_git(
+                &fixture.work,
+                &["merge-base", "--is-ancestor", commit, "HEAD"]
+            )
+            .is_ok(),
+            "{} should be merged",
+            commit
+        );
+    }
+    for name in ["origin", "mirror"] {
+        assert_eq!(
+            rev_parse(&fixture.remote_path(name), "refs/heads/main"),
+            Some(merged.clone())
+        );
+    }
+}
+
+#[test]
+fn test_reconcile_merge_confirms_mirror_deletions() {
+    let fixture = Fixture::new(&["origin", "mirror", "backup"]);
+    fixture.commit(
+        ".andiamo.toml",
+        "[[remotes]]\nname = \"origin\"\n\n[[remotes]]\nname = \"mirror\"\n\n[[remotes]]\nname = \"backup\"\nmode = \"mirror\"\n",
+        "Add andiamo config",
+    );
+    let (_, ours, _) = diverge(&fixture);
+    run_git(&fixture.work, &["branch", "old"]).unwrap();
+    fixture.andiamo(&["push", "--remotes", "backup"]);
+    run_git(&fixture.work, &["branch", "-D", "old"]).unwrap();
+    let backup = fixture.remote_path("backup");
+    assert!(rev_parse(&backup, "refs/heads/old").is_some());
+
+    let preview = fixture.andiamo(&["reconcile", "--strategy", "merge", "--dry-run"]);
+    assert!(preview.status.success(), "{}", stderr(&preview));
+    assert!(stdout(&preview).contains("The push would delete:\n  backup: refs/heads/old"));
+
+    let refused = fixture.andiamo(&["reconcile", "--strategy", "merge", "--non-interactive"]);
+    assert_eq!(refused.status.code(), Some(2));
+    assert!(stderr(&refused).contains("Pass --yes"));
+    assert_eq!(fixture.head(), ours);
+    assert!(rev_parse(&backup, "refs/heads/old").is_some());
+
+    let output = fixture.andiamo(&["reconcile", "--strategy", "merge", "--yes"]);
+
+    assert!(
+        output.status.success(),
+        "reconcile failed: {}",
+        stderr(&output)
+    );
+    assert_eq!(rev_parse(&backup, "refs/heads/old"), None);
+    assert_eq!(rev_parse(&backup, "refs/heads/main"), Some(fixture.head()));
+}
+
+#[test]
+fn test_reconcile_review_branch() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let (_, _, theirs) = diverge(&fixture);
+
+    let preview = fixture.andiamo(&["reconcile", "--strategy", "review", "--dry-run"]);
+    assert!(
+        stdout(&preview)
+            .contains("Would run: git branch review/mirror/main refs/remotes/mirror/main")
+    );
+    assert!(rev_parse(&fixture.work, "refs/heads/review/mirror/main").is_none());
+
+    let output = fixture.andiamo(&["reconcile", "--strategy", "review"]);
+
+    assert!(
+        output.status.success(),
+        "reconcile failed: {}",
+        stderr(&output)
+    );
+    assert!(
+        stdout(&output).contains("Created review/mirror/main with the 1 commit(s) only mirror has")
+    );
+    assert_eq!(
+        rev_parse(&fixture.work, "refs/heads/review/mirror/main"),
+        Some(theirs.clone())
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(theirs)
+    );
+}
+
+#[test]
+fn test_reconcile_without_divergence() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+
+    let output = fixture.andiamo(&["reconcile", "--strategy", "overwrite"]);
+
+    assert!(
+        output.status.success(),
+        "reconcile failed: {}",
+        stderr(&output)
+    );
+    assert!(stdout(&output).contains("nothing to reconcile"));
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::fs;
+use std::process::Output;
+
+use common::{Fixture, andiamo_in, rev_parse, run_git, stderr, stdout};
+
+/// Runs `andiamo relay` from the fixture's root directory, which is not a
+/// work tree, with the origin and mirror URLs on the command line.
+fn relay(fixture: &Fixture, args: &[&str]) -> Output {
+    let origin = fixture.remote_path("origin");
+    let mirror = fixture.remote_path("mirror");
+    let cache = fixture.root.path().join("cache.git");
+    let mut full = vec![
+        "relay",
+        "--origin-url",
+        origin.to_str().unwrap(),
+        "--mirror-url",
+        mirror.to_str().unwrap(),
+        "--cache",
+        cache.to_str().unwrap(),
+    ];
+    full.extend_from_slice(args);
+    andiamo_in(fixture.root.path(), &full, &[])
+}
+
+#[test]
+fn test_relay_copies_branches_and_tags_outside_a_work_tree() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["branch", "feature"]).unwrap();
+    run_git(&fixture.work, &["tag", "v1.0"]).unwrap();
+    fixture.andiamo(&["push", "--remotes", "origin", "--all", "--tags"]);
+
+    let output = relay(&fixture, &[]);
+
+    assert!(output.status.success(), "relay failed: {}", stderr(&output));
+    let report = stdout(&output);
+    assert!(report.contains("Created relay cache"), "{}", report);
+    assert!(report.contains("Relay report from origin:"), "{}", report);
+    assert!(report.contains("mirror  success"), "{}", report);
+    let mirror = fixture.remote_path("mirror");
+    for name in ["refs/heads/main", "refs/heads/feature", "refs/tags/v1.0"] {
+        assert_eq!(rev_parse(&mirror, name), Some(fixture.head()), "{}", name);
+    }
+    assert_eq!(
+        run_git(
+            &fixture.root.path().join("cache.git"),
+            &["rev-parse", "--is-bare-repository"]
+        )
+        .unwrap()
+        .trim(),
+        "true"
+    );
+
+    // The cache is reused, and there is nothing new to relay.
+    fixture.commit("next.txt", "next", "Next commit");
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+    let output = relay(&fixture, &[]);
+
+    assert!(output.status.success(), "relay failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("1 ref(s) updated"));
+    assert!(!stdout(&output).contains("Created relay cache"));
+    assert_eq!(rev_parse(&mirror, "refs/heads/main"), Some(fixture.head()));
+}
+
+#[test]
+fn test_relay_only_selected_refs() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["branch", "feature"]).unwrap();
+    run_git(&fixture.work, &["tag", "v1.0"]).unwrap();
+    fixture.andiamo(&["push", "--remotes", "origin", "--all", "--tags"]);
+
+    let output = relay(&fixture, &["--ref", "refs/heads/main"]);
+
+    assert!(output.status.success(), "relay failed: {}", stderr(&output));
+    let mirror = fixture.remote_path("mirror");
+    assert_eq!(rev_parse(&mirror, "refs/heads/main"), Some(fixture.head()));
+    assert_eq!(rev_parse(&mirror, "refs/heads/feature"), None);
+    assert_eq!(rev_parse(&mirror, "refs/tags/v1.0"), None);
+}
+
+#[test]
+fn test_relay_dry_run_leaves_destinations_alone() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+
+    let output = relay(&fixture, &["--dry-run"]);
+
+    assert!(output.status.success(), "relay failed: {}", stderr(&output));
+    let report = stdout(&output);
+    assert!(
+        report.contains("Dry run of relay from origin:"),
+        "{}",
+        report
+    );
+    assert!(report.contains("branch main: created"), "{}", report);
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        None
+    );
+}
+
+#[test]
+fn test_relay_prune_deletes_branches_gone_from_source() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["branch", "feature"]).unwrap();
+    fixture.andiamo(&["push", "--all"]);
+    run_git(&fixture.remote_path("origin"), &["branch", "-D", "feature"]).unwrap();
+
+    let kept = relay(&fixture, &[]);
+    assert!(kept.status.success(), "relay failed: {}", stderr(&kept));
+    let mirror = fixture.remote_path("mirror");
+    assert!(rev_parse(&mirror, "refs/heads/feature").is_some());
+
+    let output = relay(&fixture, &["--prune"]);
+
+    assert!(output.status.success(), "relay failed: {}", stderr(&output));
+    assert_eq!(rev_parse(&mirror, "refs/heads/feature"), None);
+    assert_eq!(rev_parse(&mirror, "refs/heads/main"), Some(fixture.head()));
+}
+
+#[test]
+fn test_relay_reports_diverged_destination_as_rejected() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    let start = fixture.head();
+    fixture.andiamo(&["push"]);
+    let theirs = fixture.commit_behind_our_back("mirror", &start, "Their commit");
+    fixture.commit("ours.txt", "ours", "Our commit");
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+
+    let output = relay(&fixture, &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("mirror  failed [rejected]"));
+    assert!(stderr(&output).contains("Push failed: could not push to mirror"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(theirs)
+    );
+}
+
+#[test]
+fn test_relay_requires_urls_and_a_bare_cache() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+
+    let output = andiamo_in(fixture.root.path(), &["relay", "--cache", "cache.git"], &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("No URL for remote 'mirror'"));
+
+    fs::create_dir(fixture.root.path().join("cache.git")).unwrap();
+    let output = relay(&fixture, &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("is not a bare git repository"));
+
+    let output = relay(&fixture, &["--ref", "main"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Invalid ref pattern 'main'"));
+}
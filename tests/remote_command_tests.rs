@@ -0,0 +1,202 @@
+mod common;
+
+use common::{Fixture, run_git, stderr, stdout};
+
+#[test]
+fn test_remote_add_single() {
+    let fixture = Fixture::new(&[]);
+
+    let output = fixture.andiamo(&[
+        "remote",
+        "add",
+        "codeberg",
+        "https://codeberg.org/me/repo.git",
+    ]);
+
+    assert!(
+        output.status.success(),
+        "remote add failed: {}",
+        stderr(&output)
+    );
+    let url = run_git(&fixture.work, &["remote", "get-url", "codeberg"]).unwrap();
+    assert_eq!(url.trim(), "https://codeberg.org/me/repo.git");
+}
+
+#[test]
+fn test_remote_list_set_url_and_remove() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(
+        &fixture.work,
+        &["remote", "add", "upstream", "https://example.com/up.git"],
+    )
+    .unwrap();
+
+    let output = fixture.andiamo(&["remote", "list"]);
+    assert!(output.status.success());
+    let listing = stdout(&output);
+    assert!(listing.contains("origin"));
+    assert!(listing.contains("mirror"));
+    assert!(listing.contains("upstream\thttps://example.com/up.git\t(not managed by andiamo)"));
+
+    let output = fixture.andiamo(&["remote", "set-url", "mirror", "https://example.com/new.git"]);
+    assert!(
+        output.status.success(),
+        "set-url failed: {}",
+        stderr(&output)
+    );
+    let url = run_git(&fixture.work, &["remote", "get-url", "mirror"]).unwrap();
+    assert_eq!(url.trim(), "https://example.com/new.git");
+
+    let output = fixture.andiamo(&["remote", "remove", "mirror"]);
+    assert!(
+        output.status.success(),
+        "remove failed: {}",
+        stderr(&output)
+    );
+    let remotes = run_git(&fixture.work, &["remote"]).unwrap();
+    assert!(!remotes.lines().any(|line| line == "mirror"));
+}
+
+#[test]
+fn test_remote_remove_missing_fails() {
+    let fixture = Fixture::new(&["origin"]);
+
+    let output = fixture.andiamo(&["remote", "remove", "mirror"]);
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Remote 'mirror' not found"));
+}
+
+#[test]
+fn test_doctor_reports_missing_remote() {
+    let fixture = Fixture::new(&["origin"]);
+
+    let output = fixture.andiamo(&["doctor"]);
+
+    assert!(!output.status.success());
+    let report = stdout(&output);
+    assert!(report.contains("[ok] remote origin"));
+    assert!(report.contains("[FAIL] remote mirror"));
+}
+
+#[test]
+fn test_doctor_healthy_repository() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+
+    let output = fixture.andiamo(&["doctor"]);
+
+    assert!(
+        output.status.success(),
+        "doctor failed: {}",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn test_remote_add_from_flags_without_prompting() {
+    let fixture = Fixture::new(&[]);
+
+    let output = fixture.andiamo(&[
+        "remote",
+        "add",
+        "--non-interactive",
+        "--origin-url",
+        "https://example.com/origin.git",
+        "--remote",
+        "mirror=https://example.com/mirror.git",
+        "--remote",
+        "nas-backup=ssh://nas/repo.git",
+    ]);
+
+    assert!(
+        output.status.success(),
+        "remote add failed: {}",
+        stderr(&output)
+    );
+    for (name, url) in [
+        ("origin", "https://example.com/origin.git"),
+        ("mirror", "https://example.com/mirror.git"),
+        ("nas-backup", "ssh://nas/repo.git"),
+    ] {
+        let actual = run_git(&fixture.work, &["remote", "get-url", name]).unwrap();
+        assert_eq!(actual.trim(), url);
+    }
+}
+
+#[test]
+fn test_remote_add_from_environment() {
+    let fixture = Fixture::new(&[]);
+
+    let output = fixture.andiamo_env(
+        &["remote", "add"],
+        &[
+            ("ANDIAMO_NON_INTERACTIVE", "true"),
+            ("ANDIAMO_ORIGIN_URL", "https://example.com/origin.git"),
+            ("ANDIAMO_MIRROR_URL", "https://example.com/mirror.git"),
+        ],
+    );
+
+    assert!(
+        output.status.success(),
+        "remote add failed: {}",
+        stderr(&output)
+    );
+    let mirror = run_git(&fixture.work, &["remote", "get-url", "mirror"]).unwrap();
+    assert_eq!(mirror.trim(), "https://example.com/mirror.git");
+}
+
+#[test]
+fn test_remote_add_missing_url_is_usage_error() {
+    let fixture = Fixture::new(&[]);
+
+    let output = fixture.andiamo(&[
+        "remote",
+        "add",
+        "--non-interactive",
+        "--origin-url",
+        "https://example.com/origin.git",
+    ]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("ANDIAMO_MIRROR_URL"));
+    let remotes = run_git(&fixture.work, &["remote"]).unwrap();
+    assert!(
+        remotes.trim().is_empty(),
+        "nothing should be added on error"
+    );
+}
+
+#[test]
+fn test_non_interactive_environment_accepts_boolish_values() {
+    for value in ["1", "yes", "on"] {
+        let fixture = Fixture::new(&[]);
+
+        let output = fixture.andiamo_env(
+            &[
+                "remote",
+                "add",
+                "--origin-url",
+                "https://example.com/origin.git",
+            ],
+            &[("ANDIAMO_NON_INTERACTIVE", value)],
+        );
+
+        assert_eq!(output.status.code(), Some(2), "{}", value);
+        assert!(
+            stderr(&output).contains("ANDIAMO_MIRROR_URL"),
+            "{}: {}",
+            value,
+            stderr(&output)
+        );
+    }
+}
+
+#[test]
+fn test_remote_add_without_tty_does_not_panic() {
+    let fixture = Fixture::new(&[]);
+
+    let output = fixture.andiamo(&["--add-remotes"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!stderr(&output).contains("panicked"));
+}
@@ -0,0 +1,81 @@
+use andiamo::git_helpers::GitError;
+use andiamo::remotes::{DEFAULT_REMOTE_NAMES, Remote, RemoteRole, RemoteSet};
+
+#[test]
+fn test_remote_set_with_defaults() {
+    let remotes = RemoteSet::with_defaults();
+
+    assert_eq!(remotes.names(), DEFAULT_REMOTE_NAMES.to_vec());
+    assert!(remotes.iter().all(|remote| remote.url.is_none()));
+}
+
+#[test]
+fn test_remote_set_preserves_order() {
+    let remotes = RemoteSet::from_names(["origin", "github-mirror", "codeberg", "nas-backup"])
+        .expect("Failed to build remote set");
+
+    assert_eq!(remotes.len(), 4);
+    assert_eq!(
+        remotes.names(),
+        vec!["origin", "github-mirror", "codeberg", "nas-backup"]
+    );
+}
+
+#[test]
+fn test_remote_set_rejects_duplicates() {
+    let mut remotes = RemoteSet::new();
+    remotes
+        .insert(Remote::new(
+            "origin",
+            Some("https://example.com/a.git".to_string()),
+        ))
+        .expect("First insert should succeed");
+
+    let result = remotes.insert(Remote::new("origin", None));
+
+    assert!(matches!(result, Err(GitError::RemoteAlreadyExists(name)) if name == "origin"));
+    assert_eq!(remotes.len(), 1);
+}
+
+#[test]
+fn test_remote_set_rejects_invalid_names() {
+    for name in ["", "has space", "-flag", "a..b", "star*"] {
+        let result = RemoteSet::from_names([name]);
+        assert!(
+            matches!(result, Err(GitError::InvalidRemoteName(_))),
+            "'{}' should be rejected",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_remote_set_lookup() {
+    let mut remotes = RemoteSet::new();
+    remotes
+        .insert(Remote::new(
+            "codeberg",
+            Some("https://codeberg.org/me/repo.git".to_string()),
+        ))
+        .expect("Insert should succeed");
+
+    assert!(remotes.contains("codeberg"));
+    assert!(!remotes.contains("origin"));
+    assert_eq!(
+        remotes
+            .get("codeberg")
+            .and_then(|remote| remote.url.as_deref()),
+        Some("https://codeberg.org/me/repo.git")
+    );
+}
+
+#[test]
+fn test_remote_set_primary() {
+    let mut remotes = RemoteSet::from_names(["mirror", "origin", "backup"]).unwrap();
+    assert_eq!(remotes.primary().unwrap().name, "origin");
+
+    remotes.get_mut("origin").unwrap().role = RemoteRole::Secondary;
+    assert_eq!(remotes.primary().unwrap().name, "mirror");
+
+    assert!(RemoteSet::new().primary().is_none());
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use andiamo::retry::{FailureKind, RetryPolicy, classify_failure};
+use common::{Fixture, report_status, rev_parse, stderr, stdout};
+use std::fs;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_classify_failure() {
+    assert_eq!(
+        classify_failure(
+            "ssh: connect to host example.com port 22: Connection timed out\nfatal: Could not read from remote repository."
+        ),
+        FailureKind::Transient
+    );
+    assert_eq!(
+        classify_failure("fatal: unable to access 'https://x/': Could not resolve host: x"),
+        FailureKind::Transient
+    );
+    assert_eq!(
+        classify_failure(
+            " ! [rejected]        main -> main (non-fast-forward)\nerror: failed to push some refs"
+        ),
+        FailureKind::Rejected
+    );
+    assert_eq!(
+        classify_failure(" ! [remote rejected] main -> main (pre-receive hook declined)"),
+        FailureKind::Rejected
+    );
+    assert_eq!(
+        classify_failure("!\trefs/heads/main:refs/heads/main\t[rejected] (stale info)"),
+        FailureKind::StaleLease
+    );
+    assert_eq!(
+        classify_failure(
+            "fatal: the receiving end does not support --atomic push\nfatal: the remote end hung up unexpectedly"
+        ),
+        FailureKind::AtomicUnsupported
+    );
+    assert_eq!(
+        classify_failure(
+            "git@github.com: Permission denied (publickey).\nfatal: the remote end hung up unexpectedly"
+        ),
+        FailureKind::Auth
+    );
+    assert_eq!(
+        classify_failure("fatal: Authentication failed for 'https://gitlab.com/x.git/'"),
+        FailureKind::Auth
+    );
+    assert_eq!(
+        classify_failure("fatal: '/nowhere' does not appear to be a git repository"),
+        FailureKind::Other
+    );
+}
+
+#[test]
+fn test_backoff_doubles_and_caps() {
+    let policy = RetryPolicy {
+        retries: 5,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(350),
+        jitter: 0.0,
+    };
+
+    assert_eq!(policy.base_delay(1), Duration::from_millis(100));
+    assert_eq!(policy.base_delay(2), Duration::from_millis(200));
+    assert_eq!(policy.base_delay(3), Duration::from_millis(350));
+    assert_eq!(policy.delay(2), Duration::from_millis(200));
+}
+
+#[test]
+fn test_jitter_stays_within_bounds() {
+    let policy = RetryPolicy {
+        retries: 1,
+        initial_backoff: Duration::from_millis(1000),
+        max_backoff: Duration::from_secs(10),
+        jitter: 0.5,
+    };
+
+    for _ in 0..50 {
+        let delay = policy.delay(1);
+        assert!(delay <= Duration::from_millis(1000));
+        assert!(delay >= Duration::from_millis(500));
+    }
+}
+
+/// Retries quickly and skips verification, so that every ssh invocation is
+/// a push attempt.
+fn fast_retry_config(fixture: &Fixture, retries: u32) {
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        format!(
+            "[push]\nverify = false\n\n[push.retry]\nretries = {}\nbackoff_ms = 10\njitter = 0.0\n",
+            retries
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_transient_failure_is_retried() {
+    let fixture = Fixture::new(&["origin"]);
+    fixture.route_over_ssh("origin");
+    fast_retry_config(&fixture, 3);
+    let ssh = fixture.ssh_wrapper(
+        "if [ $n -le 2 ]; then echo 'ssh: connect to host fake-host port 22: Connection timed out' >&2; exit 255; fi",
+    );
+
+    let output = fixture.andiamo_env(
+        &["push", "--remotes", "origin"],
+        &[("GIT_SSH_COMMAND", &ssh)],
+    );
+
+    assert!(output.status.success(), "push failed: {}", stderr(&output));
+    assert_eq!(fixture.ssh_invocations(), 3);
+    assert_eq!(
+        report_status(&stdout(&output), "origin").as_deref(),
+        Some("success after 3 attempts")
+    );
+    assert_eq!(
+        rev_parse(&fixture.remote_path("origin"), "refs/heads/main"),
+        Some(fixture.head())
+    );
+}
+
+#[test]
+fn test_retries_are_exhausted() {
+    let fixture = Fixture::new(&["origin"]);
+    fixture.route_over_ssh("origin");
+    fast_retry_config(&fixture, 2);
+    let ssh =
+        fixture.ssh_wrapper("echo 'fatal: the remote end hung up unexpectedly' >&2; exit 255");
+
+    let output = fixture.andiamo_env(
+        &["push", "--remotes", "origin"],
+        &[("GIT_SSH_COMMAND", &ssh)],
+    );
+
+    assert!(!output.status.success());
+    assert_eq!(fixture.ssh_invocations(), 3);
+    assert_eq!(
+        report_status(&stdout(&output), "origin").as_deref(),
+        Some("failed [network] after 3 attempts")
+    );
+}
+
+#[test]
+fn test_auth_failure_is_not_retried() {
+    let fixture = Fixture::new(&["origin"]);
+    fixture.route_over_ssh("origin");
+    fast_retry_config(&fixture, 3);
+    let ssh =
+        fixture.ssh_wrapper("echo 'git@fake-host: Permission denied (publickey).' >&2; exit 255");
+
+    let output = fixture.andiamo_env(
+        &["push", "--remotes", "origin"],
+        &[("GIT_SSH_COMMAND", &ssh)],
+    );
+
+    assert!(!output.status.success());
+    assert_eq!(fixture.ssh_invocations(), 1);
+    assert_eq!(
+        report_status(&stdout(&output), "origin").as_deref(),
+        Some("failed [auth]")
+    );
+}
+
+#[test]
+fn test_stalled_push_times_out_and_other_remotes_continue() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.route_over_ssh("origin");
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[push.retry]\nretries = 0\n\n[[remotes]]\nname = \"origin\"\ntimeout_secs = 1\n\n[[remotes]]\nname = \"mirror\"\n",
+    )
+    .unwrap();
+    let ssh = fixture.ssh_wrapper("sleep 30");
+
+    let start = Instant::now();
+    let output = fixture.andiamo_env(&["push", "--keep-going"], &[("GIT_SSH_COMMAND", &ssh)]);
+
+    assert!(start.elapsed() < Duration::from_secs(15));
+    assert_eq!(output.status.code(), Some(3));
+    let report = stdout(&output);
+    assert_eq!(
+        report_status(&report, "origin").as_deref(),
+        Some("failed [timeout]")
+    );
+    assert_eq!(report_status(&report, "mirror").as_deref(), Some("success"));
+    assert!(report.contains("Remote 'origin' timed out after"));
+    assert_eq!(
+        rev_parse(&fixture.remote_path("mirror"), "refs/heads/main"),
+        Some(fixture.head())
+    );
+}
+
+#[test]
+fn test_timeout_flag_overrides_config() {
+    let fixture = Fixture::new(&["origin"]);
+    fixture.route_over_ssh("origin");
+    fs::write(
+        fixture.work.join(".andiamo.toml"),
+        "[push]\ntimeout_secs = 600\n\n[push.retry]\nretries = 0\n",
+    )
+    .unwrap();
+    let ssh = fixture.ssh_wrapper("sleep 30");
+
+    let start = Instant::now();
+    let output = fixture.andiamo_env(
+        &["push", "--remotes", "origin", "--timeout", "1"],
+        &[("GIT_SSH_COMMAND", &ssh)],
+    );
+
+    assert!(start.elapsed() < Duration::from_secs(15));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        report_status(&stdout(&output), "origin").as_deref(),
+        Some("failed [timeout]")
+    );
+}
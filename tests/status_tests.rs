@@ -0,0 +1,141 @@
+mod common;
+
+use common::{Fixture, andiamo_in, run_git, stderr, stdout};
+
+#[test]
+fn test_status_shows_ahead_and_behind_per_remote() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+    fixture.commit("next.txt", "next", "Next commit");
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+    fixture.commit("local.txt", "local", "Local commit");
+
+    let output = fixture.andiamo(&["status"]);
+
+    assert!(
+        output.status.success(),
+        "status failed: {}",
+        stderr(&output)
+    );
+    let status = stdout(&output);
+    assert!(status.contains("On branch main"));
+    assert!(status.contains("Using cached remote-tracking refs"));
+    assert!(status.contains("origin  1 behind local"));
+    assert!(status.contains("mirror  2 behind local"));
+    assert!(status.contains("mirror is 1 behind origin"));
+}
+
+#[test]
+fn test_status_fetch_sees_commits_pushed_elsewhere() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push"]);
+
+    // Push to the mirror by path, leaving its remote-tracking ref stale.
+    run_git(&fixture.work, &["checkout", "-q", "-b", "other"]).unwrap();
+    fixture.commit("theirs.txt", "theirs", "Their commit");
+    let mirror_path = fixture.remote_path("mirror");
+    run_git(
+        &fixture.work,
+        &["push", mirror_path.to_str().unwrap(), "other:main"],
+    )
+    .unwrap();
+    run_git(&fixture.work, &["checkout", "-q", "main"]).unwrap();
+
+    let cached = stdout(&fixture.andiamo(&["status"]));
+    assert!(cached.contains("mirror  in sync with local"));
+
+    let fetched = stdout(&fixture.andiamo(&["status", "--fetch"]));
+    assert!(!fetched.contains("Using cached"));
+    assert!(fetched.contains("origin  in sync with local"));
+    assert!(fetched.contains("mirror  1 ahead of local"));
+    assert!(fetched.contains("mirror is 1 ahead of origin"));
+}
+
+#[test]
+fn test_status_all_branches() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["branch", "develop"]).unwrap();
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+
+    let status = stdout(&fixture.andiamo(&["status", "--all"]));
+
+    assert!(status.contains("On branch develop"));
+    assert!(status.contains("On branch main"));
+    assert!(status.contains("origin  no remote-tracking branch"));
+    assert!(status.contains("mirror  no remote-tracking branch"));
+}
+
+#[test]
+fn test_status_json() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+    fixture.commit("local.txt", "local", "Local commit");
+
+    let output = fixture.andiamo(&["status", "--json"]);
+
+    assert!(
+        output.status.success(),
+        "status failed: {}",
+        stderr(&output)
+    );
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["fetched"], false);
+    let branch = &json["branches"][0];
+    assert_eq!(branch["branch"], "main");
+    assert_eq!(branch["local"], fixture.head());
+    assert_eq!(branch["remotes"][0]["remote"], "origin");
+    assert_eq!(branch["remotes"][0]["state"], "tracked");
+    assert_eq!(branch["remotes"][0]["ahead"], 0);
+    assert_eq!(branch["remotes"][0]["behind"], 1);
+    assert_eq!(branch["remotes"][1]["state"], "missing");
+}
+
+#[test]
+fn test_status_detached_head() {
+    let fixture = Fixture::new(&["origin", "mirror"]);
+    run_git(&fixture.work, &["checkout", "-q", "--detach"]).unwrap();
+
+    let status = stdout(&fixture.andiamo(&["status"]));
+
+    assert!(status.contains(&format!("HEAD detached at {}", &fixture.head()[..7])));
+    assert!(!status.contains("On branch"));
+}
+
+#[test]
+fn test_status_in_repository_without_commits() {
+    let fixture = Fixture::new(&["origin"]);
+    fixture.andiamo(&["push", "--remotes", "origin"]);
+    let commits = run_git(&fixture.work, &["rev-list", "--count", "HEAD"]).unwrap();
+    let fresh = fixture.root.path().join("fresh");
+    run_git(
+        fixture.root.path(),
+        &["init", "-q", "-b", "main", fresh.to_str().unwrap()],
+    )
+    .unwrap();
+    let origin = fixture.remote_path("origin");
+    run_git(
+        &fresh,
+        &["remote", "add", "origin", origin.to_str().unwrap()],
+    )
+    .unwrap();
+    run_git(&fresh, &["fetch", "-q", "origin"]).unwrap();
+
+    let output = andiamo_in(&fresh, &["status", "--remotes", "origin"], &[]);
+
+    assert!(
+        output.status.success(),
+        "status failed: {}",
+        stderr(&output)
+    );
+    let status = stdout(&output);
+    assert!(
+        status.contains("On branch main (no commits yet)"),
+        "{}",
+        status
+    );
+    assert!(
+        status.contains(&format!("origin  {} ahead of local", commits.trim())),
+        "{}",
+        status
+    );
+}